@@ -0,0 +1,72 @@
+#!/usr/bin/env python3
+"""Regenerates src/assigned_numbers/tables.rs from the Bluetooth SIG assigned numbers.
+
+Usage: generate_assigned_numbers.py <path to a checkout of https://bitbucket.org/bluetooth-SIG/public>
+
+Run `cargo fmt` afterwards.
+"""
+
+import sys
+from pathlib import Path
+
+import yaml
+
+OUTPUT = Path(__file__).resolve().parent.parent / "src" / "assigned_numbers" / "tables.rs"
+
+
+def load(root, path, key):
+    with open(root / "assigned_numbers" / path) as file:
+        return yaml.safe_load(file)[key]
+
+
+def table(name, entries, doc=None, width=4):
+    lines = [f"/// {doc}"] if doc else []
+    lines.append(f"pub(super) const {name}: &[(u16, &str)] = &[")
+    for value, label in sorted(entries):
+        escaped = label.replace("\\", "\\\\").replace('"', '\\"')
+        lines.append(f'    (0x{value:0{width}X}, "{escaped}"),')
+    lines.append("];")
+    return "\n".join(lines)
+
+
+def main(root):
+    companies = [
+        (entry["value"], entry["name"])
+        for entry in load(root, "company_identifiers/company_identifiers.yaml", "company_identifiers")
+    ]
+    services = [(entry["uuid"], entry["name"]) for entry in load(root, "uuids/service_uuids.yaml", "uuids")]
+    characteristics = [
+        (entry["uuid"], entry["name"]) for entry in load(root, "uuids/characteristic_uuids.yaml", "uuids")
+    ]
+    descriptors = [(entry["uuid"], entry["name"]) for entry in load(root, "uuids/descriptors.yaml", "uuids")]
+    categories = []
+    subcategories = []
+    for category in load(root, "core/appearance_values.yaml", "appearance_values"):
+        categories.append((category["category"], category["name"]))
+        for subcategory in category.get("subcategory", []):
+            subcategories.append(((category["category"] << 6) | subcategory["value"], subcategory["name"]))
+
+    tables = [
+        table("COMPANIES", companies),
+        table("SERVICES", services),
+        table("CHARACTERISTICS", characteristics),
+        table("DESCRIPTORS", descriptors),
+        table(
+            "APPEARANCE_CATEGORIES",
+            categories,
+            "Keyed by category (upper 10 bits of the appearance value).",
+            width=3,
+        ),
+        table("APPEARANCE_SUBCATEGORIES", subcategories, "Keyed by the full appearance value."),
+    ]
+    header = (
+        "//! Generated by `scripts/generate_assigned_numbers.py` from the Bluetooth SIG assigned numbers\n"
+        "//! (https://bitbucket.org/bluetooth-SIG/public). Do not edit by hand.\n"
+    )
+    OUTPUT.write_text(header + "\n" + "\n\n".join(tables) + "\n")
+
+
+if __name__ == "__main__":
+    if len(sys.argv) != 2:
+        sys.exit(__doc__)
+    main(Path(sys.argv[1]))
@@ -0,0 +1,206 @@
+use crate::peripheral_id::PeripheralId;
+use crate::{Error, Result};
+use btleplug::api::BDAddr;
+use std::sync::{Arc, LazyLock, RwLock};
+
+static ACCESS_LIST: LazyLock<RwLock<AccessList>> =
+    LazyLock::new(|| RwLock::new(AccessList::default()));
+
+/// Process-wide list of devices that scans and connections may (or may not) touch.
+///
+/// Blocked entries always win. When any allowed entry is present, only devices matching an allowed
+/// entry are permitted. Address patterns are Bluetooth addresses (e.g. `AA:BB:CC:DD:EE:FF`) where
+/// `*` matches any run of characters and `?` matches a single character, compared
+/// case-insensitively.
+///
+/// CoreBluetooth does not expose device addresses (btleplug reports `00:00:00:00:00:00` for every
+/// device), so address patterns could never match there; [`set_access_list`] rejects them on macOS.
+#[derive(Clone, Default, uniffi::Record)]
+pub struct AccessList {
+    pub allowed_ids: Vec<Arc<PeripheralId>>,
+    pub allowed_addresses: Vec<String>,
+    pub blocked_ids: Vec<Arc<PeripheralId>>,
+    pub blocked_addresses: Vec<String>,
+}
+
+impl AccessList {
+    fn permits(&self, id: &PeripheralId, address: &str) -> bool {
+        let matches = |ids: &[Arc<PeripheralId>], patterns: &[String]| {
+            ids.iter().any(|allowed| **allowed == *id)
+                || patterns
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, address))
+        };
+        if matches(&self.blocked_ids, &self.blocked_addresses) {
+            return false;
+        }
+        if self.allowed_ids.is_empty() && self.allowed_addresses.is_empty() {
+            return true;
+        }
+        matches(&self.allowed_ids, &self.allowed_addresses)
+    }
+
+    /// Decides from the ID alone, before the device has been found. Only denies what
+    /// [`Self::permits`] is certain to deny once the address is known.
+    fn permits_id(&self, id: &PeripheralId) -> bool {
+        if let Some(address) = id.bd_addr() {
+            return self.permits(id, &address.to_string());
+        }
+        if self.blocked_ids.iter().any(|blocked| **blocked == *id) {
+            return false;
+        }
+        self.allowed_ids.is_empty()
+            || !self.allowed_addresses.is_empty()
+            || self.allowed_ids.iter().any(|allowed| **allowed == *id)
+    }
+}
+
+/// Replaces the process-wide access list.
+#[uniffi::export]
+pub fn set_access_list(list: AccessList) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    if !list.allowed_addresses.is_empty() || !list.blocked_addresses.is_empty() {
+        return Err(Error::NotSupported(
+            "Address patterns are not supported on macOS, use IDs instead".to_string(),
+        ));
+    }
+    for pattern in list.allowed_addresses.iter().chain(&list.blocked_addresses) {
+        validate_pattern(pattern)?;
+    }
+    *ACCESS_LIST.write().unwrap() = list;
+    Ok(())
+}
+
+#[uniffi::export]
+pub fn access_list() -> AccessList {
+    ACCESS_LIST.read().unwrap().clone()
+}
+
+pub(crate) fn is_permitted(id: &PeripheralId, address: BDAddr) -> bool {
+    ACCESS_LIST
+        .read()
+        .unwrap()
+        .permits(id, &address.to_string())
+}
+
+pub(crate) fn check(id: &PeripheralId, address: BDAddr) -> Result<()> {
+    if is_permitted(id, address) {
+        Ok(())
+    } else {
+        Err(Error::Blocked)
+    }
+}
+
+/// Checks the rules that apply to `id` before connecting, so that a blocked device fails right away
+/// instead of being scanned for; [`check`] applies the remaining rules once its address is known.
+pub(crate) fn check_id(id: &PeripheralId) -> Result<()> {
+    if ACCESS_LIST.read().unwrap().permits_id(id) {
+        Ok(())
+    } else {
+        Err(Error::Blocked)
+    }
+}
+
+fn validate_pattern(pattern: &str) -> Result<()> {
+    if pattern.is_empty()
+        || !pattern
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || matches!(c, ':' | '*' | '?'))
+    {
+        return Err(Error::InvalidBDAddr(format!(
+            "Invalid address pattern: {pattern}"
+        )));
+    }
+    Ok(())
+}
+
+fn matches_pattern(pattern: &str, address: &str) -> bool {
+    let pattern = pattern.to_ascii_uppercase().into_bytes();
+    let address = address.to_ascii_uppercase().into_bytes();
+
+    // Iterative wildcard match, backtracking to the most recent `*` on mismatch.
+    let (mut p, mut a) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while a < address.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == address[a]) {
+            p += 1;
+            a += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, a));
+            p += 1;
+        } else if let Some((star_p, star_a)) = star {
+            p = star_p + 1;
+            a = star_a + 1;
+            star = Some((star_p, star_a + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "AA:BB:CC:DD:EE:FF";
+
+    #[test]
+    fn exact_pattern_matches_whole_address() {
+        assert!(matches_pattern(ADDRESS, ADDRESS));
+        assert!(!matches_pattern("AA:BB:CC:DD:EE:00", ADDRESS));
+        assert!(!matches_pattern("AA:BB:CC", ADDRESS));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches_pattern("*", ADDRESS));
+        assert!(matches_pattern("AA:BB:*", ADDRESS));
+        assert!(matches_pattern("*:EE:FF", ADDRESS));
+        assert!(matches_pattern("AA:*:FF", ADDRESS));
+        assert!(matches_pattern("A?:BB:CC:DD:EE:F?", ADDRESS));
+        assert!(!matches_pattern("AA:BB:CC:DD:EE:FF?", ADDRESS));
+        assert!(!matches_pattern("BB:*", ADDRESS));
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        assert!(matches_pattern("aa:bb:*", ADDRESS));
+        assert!(matches_pattern("AA:BB:*", "aa:bb:cc:dd:ee:ff"));
+    }
+
+    #[test]
+    fn malformed_patterns_are_rejected() {
+        assert!(validate_pattern("AA:BB:*").is_ok());
+        assert!(validate_pattern("").is_err());
+        assert!(validate_pattern("GG:BB:CC:DD:EE:FF").is_err());
+        assert!(validate_pattern("AA-BB-CC-DD-EE-FF").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ids_are_checked_before_the_device_is_found() {
+        let id = |path: &str| Arc::new(PeripheralId::new(path.to_string()).unwrap());
+        let blocked = id("hci0/dev_AA_BB_CC_DD_EE_FF");
+        let other = id("hci0/dev_11_22_33_44_55_66");
+        let list = AccessList {
+            blocked_ids: vec![blocked.clone()],
+            ..AccessList::default()
+        };
+        assert!(!list.permits_id(&blocked));
+        assert!(list.permits_id(&other));
+
+        let list = AccessList {
+            blocked_addresses: vec!["11:22:*".to_string()],
+            ..AccessList::default()
+        };
+        assert!(!list.permits_id(&other));
+
+        let list = AccessList {
+            allowed_ids: vec![blocked.clone()],
+            ..AccessList::default()
+        };
+        assert!(list.permits_id(&blocked));
+        assert!(!list.permits_id(&other));
+    }
+}
@@ -0,0 +1,33 @@
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, uniffi::Enum)]
+pub enum AddressType {
+    Public,
+    Random,
+}
+
+impl AddressType {
+    /// Value of the `AddressType` property as understood by BlueZ.
+    pub fn bluez_name(self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Random => "random",
+        }
+    }
+}
+
+impl From<AddressType> for btleplug::api::AddressType {
+    fn from(value: AddressType) -> Self {
+        match value {
+            AddressType::Public => Self::Public,
+            AddressType::Random => Self::Random,
+        }
+    }
+}
+
+impl From<btleplug::api::AddressType> for AddressType {
+    fn from(value: btleplug::api::AddressType) -> Self {
+        match value {
+            btleplug::api::AddressType::Public => Self::Public,
+            btleplug::api::AddressType::Random => Self::Random,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Bluetooth SIG Assigned Numbers, compiled into the library so lookups work offline.
+//!
+//! Tables are sorted by value and searched with a binary search. They currently hold a subset of
+//! the assigned numbers (see `scripts/generate_assigned_numbers.py` to generate the full tables), so
+//! a missing name does not mean the value is unassigned.
+
+mod tables;
+
+use crate::uuid::Uuid;
+use tables::{
+    APPEARANCE_CATEGORIES, APPEARANCE_SUBCATEGORIES, CHARACTERISTICS, COMPANIES, DESCRIPTORS,
+    SERVICES,
+};
+
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct Appearance {
+    pub category: String,
+    pub subcategory: Option<String>,
+}
+
+/// Name of the company with the given identifier, as used in manufacturer specific data.
+#[uniffi::export]
+pub fn company_name(id: u16) -> Option<String> {
+    lookup(COMPANIES, id)
+}
+
+#[uniffi::export]
+pub fn service_name(uuid: Uuid) -> Option<String> {
+    lookup(SERVICES, uuid.to_u16()?)
+}
+
+#[uniffi::export]
+pub fn characteristic_name(uuid: Uuid) -> Option<String> {
+    lookup(CHARACTERISTICS, uuid.to_u16()?)
+}
+
+#[uniffi::export]
+pub fn descriptor_name(uuid: Uuid) -> Option<String> {
+    lookup(DESCRIPTORS, uuid.to_u16()?)
+}
+
+/// Decodes an appearance value (category in the upper 10 bits, subcategory in the lower 6 bits).
+/// An unknown subcategory of a known category decodes to the category alone.
+#[uniffi::export]
+pub fn appearance(value: u16) -> Option<Appearance> {
+    let category = lookup(APPEARANCE_CATEGORIES, value >> 6)?;
+    let subcategory = match value & 0x3F {
+        0 => None,
+        _ => lookup(APPEARANCE_SUBCATEGORIES, value),
+    };
+    Some(Appearance {
+        category,
+        subcategory,
+    })
+}
+
+fn lookup(table: &[(u16, &str)], key: u16) -> Option<String> {
+    table
+        .binary_search_by_key(&key, |(value, _)| *value)
+        .ok()
+        .map(|index| table[index].1.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_are_sorted() {
+        for table in [
+            COMPANIES,
+            SERVICES,
+            CHARACTERISTICS,
+            DESCRIPTORS,
+            APPEARANCE_CATEGORIES,
+            APPEARANCE_SUBCATEGORIES,
+        ] {
+            assert!(table.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+    }
+
+    #[test]
+    fn known_values_are_found() {
+        assert_eq!(company_name(0x0000).as_deref(), Some("Ericsson AB"));
+        assert_eq!(
+            service_name(Uuid::from_u16(0x180D)).as_deref(),
+            Some("Heart Rate")
+        );
+        assert_eq!(
+            characteristic_name(Uuid::from_u16(0x2A37)).as_deref(),
+            Some("Heart Rate Measurement")
+        );
+        assert_eq!(
+            descriptor_name(Uuid::from_u16(0x2902)).as_deref(),
+            Some("Client Characteristic Configuration")
+        );
+    }
+
+    #[test]
+    fn unknown_values_are_not_found() {
+        assert_eq!(service_name(Uuid::from_u16(0x0001)), None);
+        assert!(appearance(0xFFC0).is_none());
+    }
+
+    #[test]
+    fn appearance_decodes_category_and_subcategory() {
+        let laptop = appearance(0x0083).unwrap();
+        assert_eq!(laptop.category, "Computer");
+        assert_eq!(laptop.subcategory.as_deref(), Some("Laptop"));
+
+        let computer = appearance(0x0080).unwrap();
+        assert_eq!(computer.category, "Computer");
+        assert_eq!(computer.subcategory, None);
+    }
+
+    #[test]
+    fn unknown_subcategory_falls_back_to_category() {
+        let computer = appearance(0x00BF).unwrap();
+        assert_eq!(computer.category, "Computer");
+        assert_eq!(computer.subcategory, None);
+    }
+}
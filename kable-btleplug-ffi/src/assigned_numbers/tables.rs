@@ -0,0 +1,520 @@
+//! Subset of the Bluetooth SIG assigned numbers (https://bitbucket.org/bluetooth-SIG/public),
+//! written by hand. Not yet generated: running `scripts/generate_assigned_numbers.py` replaces this
+//! file with the complete tables.
+
+pub(super) const COMPANIES: &[(u16, &str)] = &[
+    (0x0000, "Ericsson AB"),
+    (0x0001, "Nokia Mobile Phones"),
+    (0x0002, "Intel Corp."),
+    (0x0003, "IBM Corp."),
+    (0x0004, "Toshiba Corp."),
+    (0x0005, "3Com"),
+    (0x0006, "Microsoft"),
+    (0x0007, "Lucent"),
+    (0x0008, "Motorola"),
+    (0x0009, "Infineon Technologies AG"),
+    (0x000A, "Qualcomm Technologies International, Ltd. (QTIL)"),
+    (0x000B, "Silicon Wave"),
+    (0x000C, "Digianswer A/S"),
+    (0x000D, "Texas Instruments Inc."),
+    (0x000E, "Parthus Technologies Inc."),
+    (0x000F, "Broadcom Corporation"),
+    (0x0010, "Mitel Semiconductor"),
+    (0x0011, "Widcomm, Inc."),
+    (0x0012, "Zeevo, Inc."),
+    (0x0013, "Atmel Corporation"),
+    (0x0014, "Mitsubishi Electric Corporation"),
+    (0x0015, "RTX A/S"),
+    (0x0016, "KC Technology Inc."),
+    (0x0017, "Newlogic"),
+    (0x0018, "Transilica, Inc."),
+    (0x0019, "Rohde & Schwarz GmbH & Co. KG"),
+    (0x001A, "TTPCom Limited"),
+    (0x001B, "Signia Technologies, Inc."),
+    (0x001C, "Conexant Systems Inc."),
+    (0x001D, "Qualcomm"),
+    (0x001E, "Inventel"),
+    (0x001F, "AVM Berlin"),
+    (0x0020, "BandSpeed, Inc."),
+    (0x0021, "Mansella Ltd"),
+    (0x0022, "NEC Corporation"),
+    (0x0023, "WavePlus Technology Co., Ltd."),
+    (0x0024, "Alcatel"),
+    (0x0025, "NXP B.V."),
+    (0x0026, "C Technologies"),
+    (0x0027, "Open Interface"),
+    (0x0028, "R F Micro Devices"),
+    (0x0029, "Hitachi Ltd"),
+    (0x002A, "Symbol Technologies, Inc."),
+    (0x002B, "Tenovis"),
+    (0x002C, "Macronix International Co. Ltd."),
+    (0x002D, "GCT Semiconductor"),
+    (0x002E, "Norwood Systems"),
+    (0x002F, "MewTel Technology Inc."),
+    (0x0030, "ST Microelectronics"),
+    (0x0031, "Synopsys, Inc."),
+    (0x0032, "Red-M (Communications) Ltd"),
+    (0x0033, "Commil Ltd"),
+    (0x0034, "Computer Access Technology Corporation (CATC)"),
+    (0x0035, "Eclipse (HQ Espana) S.L."),
+    (0x0036, "Renesas Electronics Corporation"),
+    (0x0037, "Mobilian Corporation"),
+    (0x0038, "Syntronix Corporation"),
+    (0x0039, "Integrated System Solution Corp."),
+    (0x003A, "Panasonic Holdings Corporation"),
+    (0x003B, "Gennum Corporation"),
+    (0x003C, "BlackBerry Limited"),
+    (0x003D, "IPextreme, Inc."),
+    (0x003E, "Systems and Chips, Inc"),
+    (0x003F, "Bluetooth SIG, Inc"),
+    (0x0040, "Seiko Epson Corporation"),
+    (0x0041, "Integrated Silicon Solution Taiwan, Inc."),
+    (0x0042, "CONWISE Technology Corporation Ltd"),
+    (0x0043, "PARROT AUTOMOTIVE SAS"),
+    (0x0044, "Socket Mobile"),
+    (0x0045, "Atheros Communications, Inc."),
+    (0x0046, "MediaTek, Inc."),
+    (0x0047, "Bluegiga"),
+    (0x0048, "Marvell Technology Group Ltd."),
+    (0x0049, "3DSP Corporation"),
+    (0x004A, "Accel Semiconductor Ltd."),
+    (0x004B, "Continental Automotive Systems"),
+    (0x004C, "Apple, Inc."),
+    (0x004D, "Staccato Communications, Inc."),
+    (0x004E, "Avago Technologies"),
+    (0x004F, "APT Ltd."),
+    (0x0050, "SiRF Technology, Inc."),
+    (0x0051, "Tzero Technologies, Inc."),
+    (0x0052, "J&M Corporation"),
+    (0x0053, "Free2move AB"),
+    (0x0054, "3DiJoy Corporation"),
+    (0x0055, "Plantronics, Inc."),
+    (0x0056, "Sony Ericsson Mobile Communications"),
+    (0x0057, "Harman International Industries, Inc."),
+    (0x0058, "Vizio, Inc."),
+    (0x0059, "Nordic Semiconductor ASA"),
+    (0x005A, "EM Microelectronic-Marin SA"),
+    (0x005B, "Ralink Technology Corporation"),
+    (0x005C, "Belkin International, Inc."),
+    (0x005D, "Realtek Semiconductor Corporation"),
+    (0x005E, "Stonestreet One, LLC"),
+    (0x005F, "Wicentric, Inc."),
+    (0x0060, "RivieraWaves S.A.S"),
+    (0x0061, "RDA Microelectronics"),
+    (0x0062, "Gibson Guitars"),
+    (0x0063, "MiCommand Inc."),
+    (0x0064, "Band XI International, LLC"),
+    (0x0065, "HP, Inc."),
+    (0x0066, "9Solutions Oy"),
+    (0x0067, "GN Audio A/S"),
+    (0x0068, "General Motors"),
+    (0x0069, "A&D Engineering, Inc."),
+    (0x006A, "LTIMINDTREE LIMITED"),
+    (0x006B, "Polar Electro OY"),
+    (0x006C, "Beautiful Enterprise Co., Ltd."),
+    (0x006D, "BriarTek, Inc"),
+    (0x006E, "Summit Data Communications, Inc."),
+    (0x006F, "Sound ID"),
+    (0x0070, "Monster, LLC"),
+    (0x0071, "connectBlue AB"),
+    (0x0072, "ShangHai Super Smart Electronics Co. Ltd."),
+    (0x0073, "Group Sense Ltd."),
+    (0x0074, "Zomm, LLC"),
+    (0x0075, "Samsung Electronics Co. Ltd."),
+    (0x0076, "Creative Technology Ltd."),
+    (0x0077, "Laird Connectivity LLC"),
+    (0x0078, "Nike, Inc."),
+    (0x0079, "lesswire AG"),
+    (0x007A, "MStar Semiconductor, Inc."),
+    (0x007B, "Hanlynn Technologies"),
+    (0x007C, "A & R Cambridge"),
+    (0x007D, "Seers Technology Co., Ltd."),
+    (0x007E, "Sports Tracking Technologies Ltd."),
+    (0x007F, "Autonet Mobile"),
+    (0x0080, "DeLorme Publishing Company, Inc."),
+    (0x0081, "WuXi Vimicro"),
+    (0x0082, "DSEA A/S"),
+    (0x0083, "TimeKeeping Systems, Inc."),
+    (0x0084, "Ludus Helsinki Ltd."),
+    (0x0085, "BlueRadios, Inc."),
+    (0x0086, "Equinux AG"),
+    (0x0087, "Garmin International, Inc."),
+    (0x0088, "Ecotest"),
+    (0x0089, "GN Hearing A/S"),
+    (0x008A, "Jawbone"),
+    (0x008B, "Topcon Positioning Systems, LLC"),
+    (0x008C, "Gimbal Inc."),
+    (0x008D, "Zscan Software"),
+    (0x008E, "Quintic Corp"),
+    (0x008F, "Telit Wireless Solutions GmbH"),
+    (0x0090, "Funai Electric Co., Ltd."),
+    (0x0091, "Advanced PANMOBIL systems GmbH & Co. KG"),
+    (0x0092, "ThinkOptics, Inc."),
+    (0x0093, "Universal Electronics, Inc."),
+    (0x0094, "Airoha Technology Corp."),
+    (0x0095, "NEC Lighting, Ltd."),
+    (0x0096, "ODM Technology, Inc."),
+    (0x0097, "ConnecteDevice Ltd."),
+    (0x0098, "zero1.tv GmbH"),
+    (0x0099, "i.Tech Dynamic Global Distribution Ltd."),
+    (0x009A, "Alpwise"),
+    (0x009B, "Jiangsu Toppower Automotive Electronics Co., Ltd."),
+    (0x009C, "Colorfy, Inc."),
+    (0x009D, "Geoforce Inc."),
+    (0x009E, "Bose Corporation"),
+    (0x009F, "Suunto Oy"),
+    (0x00A0, "Kensington Computer Products Group"),
+    (0x00A1, "SR-Medizinelektronik"),
+    (0x00A2, "Vertu Corporation Limited"),
+    (0x00A3, "Meta Watch Ltd."),
+    (0x00A4, "LINAK A/S"),
+    (0x00A5, "OTL Dynamics LLC"),
+    (0x00A6, "Panda Ocean Inc."),
+    (0x00A7, "Visteon Corporation"),
+    (0x00A8, "ARP Devices Limited"),
+    (0x00A9, "MARELLI EUROPE S.P.A."),
+    (0x00AA, "CAEN RFID srl"),
+    (0x00AB, "Ingenieur-Systemgruppe Zahn GmbH"),
+    (0x00AC, "Green Throttle Games"),
+    (0x00AD, "Peter Systemtechnik GmbH"),
+    (0x00AE, "Omegawave Oy"),
+    (0x00AF, "Cinetix"),
+    (0x00B0, "Passif Semiconductor Corp"),
+    (0x00B1, "Saris Cycling Group, Inc"),
+    (0x00B2, "Bekey A/S"),
+    (0x00B3, "Clarinox Technologies Pty. Ltd."),
+    (0x00B4, "BDE Technology Co., Ltd."),
+    (0x00B5, "Swirl Networks"),
+    (0x00B6, "Meso international"),
+    (0x00B7, "TreLab Ltd"),
+    (0x00B8, "Qualcomm Innovation Center, Inc. (QuIC)"),
+    (0x00B9, "Johnson Controls, Inc."),
+    (0x00BA, "Starkey Hearing Technologies"),
+    (0x00BB, "S-Power Electronics Limited"),
+    (0x00BC, "Ace Sensor Inc"),
+    (0x00BD, "Aplix Corporation"),
+    (0x00BE, "AAMP of America"),
+    (0x00BF, "Stalmart Technology Limited"),
+    (0x00C0, "AMICCOM Electronics Corporation"),
+    (0x00C1, "Shenzhen Excelsecu Data Technology Co.,Ltd"),
+    (0x00C2, "Geneq Inc."),
+    (0x00C3, "adidas AG"),
+    (0x00C4, "LG Electronics"),
+    (0x00C5, "Onset Computer Corporation"),
+    (0x00C6, "Selfly BV"),
+    (0x00C7, "Quuppa Oy."),
+    (0x00C8, "GeLo Inc"),
+    (0x00C9, "Evluma"),
+    (0x00CA, "MC10"),
+    (0x00CB, "Binauric SE"),
+    (0x00CC, "Beats Electronics"),
+    (0x00CD, "Microchip Technology Inc."),
+    (0x00CE, "Eve Systems GmbH"),
+    (0x00CF, "ARCHOS SA"),
+    (0x00D0, "Dexcom, Inc."),
+    (0x00E0, "Google"),
+    (0x0131, "Cypress Semiconductor"),
+    (0x0157, "Anhui Huami Information Technology Co., Ltd."),
+    (0x0171, "Amazon.com Services LLC"),
+    (0x027D, "HUAWEI Technologies Co., Ltd."),
+    (0x02E5, "Espressif Systems (Shanghai) Co., Ltd."),
+    (0x02FF, "Silicon Laboratories"),
+    (0x038F, "Xiaomi Inc."),
+    (0x0499, "Ruuvi Innovations Ltd."),
+];
+
+pub(super) const SERVICES: &[(u16, &str)] = &[
+    (0x1800, "Generic Access"),
+    (0x1801, "Generic Attribute"),
+    (0x1802, "Immediate Alert"),
+    (0x1803, "Link Loss"),
+    (0x1804, "Tx Power"),
+    (0x1805, "Current Time"),
+    (0x1806, "Reference Time Update"),
+    (0x1807, "Next DST Change"),
+    (0x1808, "Glucose"),
+    (0x1809, "Health Thermometer"),
+    (0x180A, "Device Information"),
+    (0x180D, "Heart Rate"),
+    (0x180E, "Phone Alert Status"),
+    (0x180F, "Battery"),
+    (0x1810, "Blood Pressure"),
+    (0x1811, "Alert Notification"),
+    (0x1812, "Human Interface Device"),
+    (0x1813, "Scan Parameters"),
+    (0x1814, "Running Speed and Cadence"),
+    (0x1815, "Automation IO"),
+    (0x1816, "Cycling Speed and Cadence"),
+    (0x1818, "Cycling Power"),
+    (0x1819, "Location and Navigation"),
+    (0x181A, "Environmental Sensing"),
+    (0x181B, "Body Composition"),
+    (0x181C, "User Data"),
+    (0x181D, "Weight Scale"),
+    (0x181E, "Bond Management"),
+    (0x181F, "Continuous Glucose Monitoring"),
+    (0x1820, "Internet Protocol Support"),
+    (0x1821, "Indoor Positioning"),
+    (0x1822, "Pulse Oximeter"),
+    (0x1823, "HTTP Proxy"),
+    (0x1824, "Transport Discovery"),
+    (0x1825, "Object Transfer"),
+    (0x1826, "Fitness Machine"),
+    (0x1827, "Mesh Provisioning"),
+    (0x1828, "Mesh Proxy"),
+    (0x1829, "Reconnection Configuration"),
+    (0x183A, "Insulin Delivery"),
+    (0x183B, "Binary Sensor"),
+    (0x183C, "Emergency Configuration"),
+    (0x183E, "Physical Activity Monitor"),
+    (0x1843, "Audio Input Control"),
+    (0x1844, "Volume Control"),
+    (0x1845, "Volume Offset Control"),
+    (0x1846, "Coordinated Set Identification"),
+    (0x1847, "Device Time"),
+    (0x1848, "Media Control"),
+    (0x1849, "Generic Media Control"),
+    (0x184A, "Constant Tone Extension"),
+    (0x184B, "Telephone Bearer"),
+    (0x184C, "Generic Telephone Bearer"),
+    (0x184D, "Microphone Control"),
+    (0x184E, "Audio Stream Control"),
+    (0x184F, "Broadcast Audio Scan"),
+    (0x1850, "Published Audio Capabilities"),
+    (0x1851, "Basic Audio Announcement"),
+    (0x1852, "Broadcast Audio Announcement"),
+    (0x1853, "Common Audio"),
+    (0x1854, "Hearing Access"),
+    (0x1855, "Telephony and Media Audio"),
+    (0x1856, "Public Broadcast Announcement"),
+];
+
+pub(super) const CHARACTERISTICS: &[(u16, &str)] = &[
+    (0x2A00, "Device Name"),
+    (0x2A01, "Appearance"),
+    (0x2A02, "Peripheral Privacy Flag"),
+    (0x2A03, "Reconnection Address"),
+    (0x2A04, "Peripheral Preferred Connection Parameters"),
+    (0x2A05, "Service Changed"),
+    (0x2A06, "Alert Level"),
+    (0x2A07, "Tx Power Level"),
+    (0x2A08, "Date Time"),
+    (0x2A09, "Day of Week"),
+    (0x2A0A, "Day Date Time"),
+    (0x2A0C, "Exact Time 256"),
+    (0x2A0D, "DST Offset"),
+    (0x2A0E, "Time Zone"),
+    (0x2A0F, "Local Time Information"),
+    (0x2A11, "Time with DST"),
+    (0x2A12, "Time Accuracy"),
+    (0x2A13, "Time Source"),
+    (0x2A14, "Reference Time Information"),
+    (0x2A16, "Time Update Control Point"),
+    (0x2A17, "Time Update State"),
+    (0x2A18, "Glucose Measurement"),
+    (0x2A19, "Battery Level"),
+    (0x2A1C, "Temperature Measurement"),
+    (0x2A1D, "Temperature Type"),
+    (0x2A1E, "Intermediate Temperature"),
+    (0x2A21, "Measurement Interval"),
+    (0x2A22, "Boot Keyboard Input Report"),
+    (0x2A23, "System ID"),
+    (0x2A24, "Model Number String"),
+    (0x2A25, "Serial Number String"),
+    (0x2A26, "Firmware Revision String"),
+    (0x2A27, "Hardware Revision String"),
+    (0x2A28, "Software Revision String"),
+    (0x2A29, "Manufacturer Name String"),
+    (
+        0x2A2A,
+        "IEEE 11073-20601 Regulatory Certification Data List",
+    ),
+    (0x2A2B, "Current Time"),
+    (0x2A31, "Scan Refresh"),
+    (0x2A32, "Boot Keyboard Output Report"),
+    (0x2A33, "Boot Mouse Input Report"),
+    (0x2A34, "Glucose Measurement Context"),
+    (0x2A35, "Blood Pressure Measurement"),
+    (0x2A36, "Intermediate Cuff Pressure"),
+    (0x2A37, "Heart Rate Measurement"),
+    (0x2A38, "Body Sensor Location"),
+    (0x2A39, "Heart Rate Control Point"),
+    (0x2A3F, "Alert Status"),
+    (0x2A40, "Ringer Control Point"),
+    (0x2A41, "Ringer Setting"),
+    (0x2A42, "Alert Category ID Bit Mask"),
+    (0x2A43, "Alert Category ID"),
+    (0x2A44, "Alert Notification Control Point"),
+    (0x2A45, "Unread Alert Status"),
+    (0x2A46, "New Alert"),
+    (0x2A47, "Supported New Alert Category"),
+    (0x2A48, "Supported Unread Alert Category"),
+    (0x2A49, "Blood Pressure Feature"),
+    (0x2A4A, "HID Information"),
+    (0x2A4B, "Report Map"),
+    (0x2A4C, "HID Control Point"),
+    (0x2A4D, "Report"),
+    (0x2A4E, "Protocol Mode"),
+    (0x2A4F, "Scan Interval Window"),
+    (0x2A50, "PnP ID"),
+    (0x2A51, "Glucose Feature"),
+    (0x2A52, "Record Access Control Point"),
+    (0x2A53, "RSC Measurement"),
+    (0x2A54, "RSC Feature"),
+    (0x2A55, "SC Control Point"),
+    (0x2A5B, "CSC Measurement"),
+    (0x2A5C, "CSC Feature"),
+    (0x2A5D, "Sensor Location"),
+    (0x2A5E, "PLX Spot-Check Measurement"),
+    (0x2A5F, "PLX Continuous Measurement"),
+    (0x2A60, "PLX Features"),
+    (0x2A63, "Cycling Power Measurement"),
+    (0x2A64, "Cycling Power Vector"),
+    (0x2A65, "Cycling Power Feature"),
+    (0x2A66, "Cycling Power Control Point"),
+    (0x2A6C, "Elevation"),
+    (0x2A6D, "Pressure"),
+    (0x2A6E, "Temperature"),
+    (0x2A6F, "Humidity"),
+    (0x2A9D, "Weight Measurement"),
+    (0x2A9E, "Weight Scale Feature"),
+    (0x2AA6, "Central Address Resolution"),
+    (0x2AA7, "CGM Measurement"),
+    (0x2AC9, "Resolvable Private Address Only"),
+    (0x2ACC, "Fitness Machine Feature"),
+    (0x2AD2, "Indoor Bike Data"),
+    (0x2AD9, "Fitness Machine Control Point"),
+    (0x2ADA, "Fitness Machine Status"),
+    (0x2B29, "Client Supported Features"),
+    (0x2B2A, "Database Hash"),
+    (0x2B3A, "Server Supported Features"),
+];
+
+pub(super) const DESCRIPTORS: &[(u16, &str)] = &[
+    (0x2900, "Characteristic Extended Properties"),
+    (0x2901, "Characteristic User Description"),
+    (0x2902, "Client Characteristic Configuration"),
+    (0x2903, "Server Characteristic Configuration"),
+    (0x2904, "Characteristic Presentation Format"),
+    (0x2905, "Characteristic Aggregate Format"),
+    (0x2906, "Valid Range"),
+    (0x2907, "External Report Reference"),
+    (0x2908, "Report Reference"),
+    (0x2909, "Number of Digitals"),
+    (0x290A, "Value Trigger Setting"),
+    (0x290B, "Environmental Sensing Configuration"),
+    (0x290C, "Environmental Sensing Measurement"),
+    (0x290D, "Environmental Sensing Trigger Setting"),
+    (0x290E, "Time Trigger Setting"),
+    (0x290F, "Complete BR-EDR Transport Block Data"),
+];
+
+/// Keyed by category (upper 10 bits of the appearance value).
+pub(super) const APPEARANCE_CATEGORIES: &[(u16, &str)] = &[
+    (0x000, "Unknown"),
+    (0x001, "Phone"),
+    (0x002, "Computer"),
+    (0x003, "Watch"),
+    (0x004, "Clock"),
+    (0x005, "Display"),
+    (0x006, "Remote Control"),
+    (0x007, "Eye-glasses"),
+    (0x008, "Tag"),
+    (0x009, "Keyring"),
+    (0x00A, "Media Player"),
+    (0x00B, "Barcode Scanner"),
+    (0x00C, "Thermometer"),
+    (0x00D, "Heart Rate Sensor"),
+    (0x00E, "Blood Pressure"),
+    (0x00F, "Human Interface Device"),
+    (0x010, "Glucose Meter"),
+    (0x011, "Running Walking Sensor"),
+    (0x012, "Cycling"),
+    (0x013, "Control Device"),
+    (0x014, "Network Device"),
+    (0x015, "Sensor"),
+    (0x016, "Light Fixtures"),
+    (0x017, "Fan"),
+    (0x018, "HVAC"),
+    (0x019, "Air Conditioning"),
+    (0x01A, "Humidifier"),
+    (0x01B, "Heating"),
+    (0x01C, "Access Control"),
+    (0x01D, "Motorized Device"),
+    (0x01E, "Power Device"),
+    (0x01F, "Light Source"),
+    (0x020, "Window Covering"),
+    (0x021, "Audio Sink"),
+    (0x022, "Audio Source"),
+    (0x023, "Motorized Vehicle"),
+    (0x024, "Domestic Appliance"),
+    (0x025, "Wearable Audio Device"),
+    (0x026, "Aircraft"),
+    (0x027, "AV Equipment"),
+    (0x028, "Display Equipment"),
+    (0x029, "Hearing aid"),
+    (0x02A, "Gaming"),
+    (0x02B, "Signage"),
+    (0x031, "Pulse Oximeter"),
+    (0x032, "Weight Scale"),
+    (0x033, "Personal Mobility Device"),
+    (0x034, "Continuous Glucose Monitor"),
+    (0x035, "Insulin Pump"),
+    (0x036, "Medication Delivery"),
+    (0x037, "Spirometer"),
+    (0x051, "Outdoor Sports Activity"),
+];
+
+/// Keyed by the full appearance value.
+pub(super) const APPEARANCE_SUBCATEGORIES: &[(u16, &str)] = &[
+    (0x0080, "Generic Computer"),
+    (0x0081, "Desktop Workstation"),
+    (0x0082, "Server-class Computer"),
+    (0x0083, "Laptop"),
+    (0x0084, "Handheld PC/PDA (clamshell)"),
+    (0x0085, "Palm-size PC/PDA"),
+    (0x0086, "Wearable computer (watch size)"),
+    (0x0087, "Tablet"),
+    (0x0088, "Docking Station"),
+    (0x0089, "All in One"),
+    (0x008A, "Blade Server"),
+    (0x008B, "Convertible"),
+    (0x008C, "Detachable"),
+    (0x008D, "IoT Gateway"),
+    (0x008E, "Mini PC"),
+    (0x008F, "Stick PC"),
+    (0x00C1, "Sports Watch"),
+    (0x00C2, "Smartwatch"),
+    (0x0301, "Ear Thermometer"),
+    (0x0341, "Heart Rate Belt"),
+    (0x0381, "Arm Blood Pressure"),
+    (0x0382, "Wrist Blood Pressure"),
+    (0x03C1, "Keyboard"),
+    (0x03C2, "Mouse"),
+    (0x03C3, "Joystick"),
+    (0x03C4, "Gamepad"),
+    (0x03C5, "Digitizer Tablet"),
+    (0x03C6, "Card Reader"),
+    (0x03C7, "Digital Pen"),
+    (0x03C8, "Barcode Scanner"),
+    (0x03C9, "Touchpad"),
+    (0x03CA, "Presentation Remote"),
+    (0x0441, "In-Shoe Running Walking Sensor"),
+    (0x0442, "On-Shoe Running Walking Sensor"),
+    (0x0443, "On-Hip Running Walking Sensor"),
+    (0x0481, "Cycling Computer"),
+    (0x0482, "Speed Sensor"),
+    (0x0483, "Cadence Sensor"),
+    (0x0484, "Power Sensor"),
+    (0x0485, "Speed and Cadence Sensor"),
+    (0x0941, "Earbud"),
+    (0x0942, "Headset"),
+    (0x0943, "Headphones"),
+    (0x0944, "Neck Band"),
+    (0x0C41, "Fingertip Pulse Oximeter"),
+    (0x0C42, "Wrist Worn Pulse Oximeter"),
+    (0x1441, "Location Display"),
+    (0x1442, "Location and Navigation Display"),
+    (0x1443, "Location Pod"),
+    (0x1444, "Location and Navigation Pod"),
+];
@@ -0,0 +1,344 @@
+//! Direct BlueZ D-Bus calls for functionality that btleplug does not expose.
+
+use crate::address_type::AddressType;
+use crate::characteristic::Characteristic;
+use crate::descriptor::Descriptor;
+use crate::disconnect_reason::DisconnectReason;
+use crate::notification_pump::{Notification, Source};
+use crate::service::Service;
+use crate::write_type::WriteType;
+use crate::{Error, Result};
+use bluez_async::{
+    BluetoothEvent, BluetoothSession, CharacteristicEvent, CharacteristicId, DeviceId, WriteOptions,
+};
+use btleplug::api::{BDAddr, Central, CharPropFlags};
+use dbus::arg::{PropMap, Variant};
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, OnceCell};
+use tokio_stream::{Stream, StreamExt};
+
+const BLUEZ_SERVICE: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+
+/// The `Disconnected` signal and btleplug's disconnect event travel over different D-Bus
+/// connections, so either may arrive first; wait this long for the signal.
+const DISCONNECT_REASON_GRACE: Duration = Duration::from_millis(250);
+
+/// Reasons older than this belong to an earlier disconnect.
+const DISCONNECT_REASON_TTL: Duration = Duration::from_secs(1);
+
+/// Most recent `Device1.Disconnected` reason per device object path.
+static DISCONNECT_REASONS: LazyLock<Mutex<HashMap<String, (Instant, DisconnectReason)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static DISCONNECT_REASON_RECORDED: Notify = Notify::const_new();
+static WATCHING_DISCONNECTS: AtomicBool = AtomicBool::new(false);
+
+/// BlueZ gives up on LE connection attempts after this long, so waiting longer is pointless.
+const CONNECT_DEVICE_TIMEOUT: Duration = Duration::from_secs(30);
+
+static ADAPTER_PATH: OnceCell<String> = OnceCell::const_new();
+static SESSION: OnceCell<BluetoothSession> = OnceCell::const_new();
+
+/// Session for the BlueZ calls made through bluez-async. Created on the shared runtime, which then
+/// runs its D-Bus connection.
+async fn session() -> Result<BluetoothSession> {
+    SESSION
+        .get_or_try_init(|| async {
+            crate::runtime::handle()?
+                .spawn(async {
+                    let (_, session) = BluetoothSession::new().await?;
+                    Ok(session)
+                })
+                .await
+                .map_err(|e| Error::RuntimeError(e.to_string()))?
+        })
+        .await
+        .cloned()
+}
+
+/// Object path of the adapter in use (see [`crate::get_adapter`]), e.g. `/org/bluez/hci0`.
+///
+/// btleplug does not expose its adapter's ID. With a single adapter there is nothing to choose from;
+/// otherwise the ID is the first word of the description btleplug reports for its adapter.
+pub(crate) async fn adapter_path() -> Result<String> {
+    ADAPTER_PATH
+        .get_or_try_init(|| async {
+            let mut adapters = session().await?.get_adapters().await?;
+            let adapter = if adapters.len() == 1 {
+                adapters.pop()
+            } else {
+                let description = crate::get_adapter().await?.adapter_info().await?;
+                let id = description.split_whitespace().next().unwrap_or_default();
+                adapters
+                    .into_iter()
+                    .find(|adapter| adapter.id.to_string() == id)
+            };
+            adapter
+                .map(|adapter| dbus::Path::from(adapter.id).to_string())
+                .ok_or_else(|| Error::Other("Unable to find the adapter in use".to_string()))
+        })
+        .await
+        .cloned()
+}
+
+/// Connects to a device by address without a prior discovery, via `Adapter1.ConnectDevice`.
+///
+/// Returns the object path of the (newly created) device. BlueZ only exposes this method when
+/// `bluetoothd` runs with experimental interfaces enabled. Dropping the returned future (e.g. on
+/// cancellation or timeout) aborts the connection attempt via `Device1.Disconnect`.
+pub(crate) async fn connect_device(
+    adapter_path: String,
+    address: BDAddr,
+    address_type: AddressType,
+) -> Result<String> {
+    let mut pending = PendingConnect(Some(format!(
+        "{adapter_path}/dev_{}",
+        address.to_string().replace(':', "_")
+    )));
+    let result = tokio::task::spawn_blocking(move || {
+        let connection = Connection::new_system()?;
+        let proxy = connection.with_proxy(BLUEZ_SERVICE, adapter_path, CONNECT_DEVICE_TIMEOUT);
+        let mut properties = PropMap::new();
+        properties.insert(
+            "Address".to_string(),
+            Variant(Box::new(address.to_string())),
+        );
+        properties.insert(
+            "AddressType".to_string(),
+            Variant(Box::new(address_type.bluez_name().to_string())),
+        );
+        let (device,): (dbus::Path<'static>,) =
+            proxy.method_call(ADAPTER_INTERFACE, "ConnectDevice", (properties,))?;
+        Ok(device.to_string())
+    })
+    .await
+    .map_err(|e| Error::RuntimeError(e.to_string()))?;
+    pending.0 = None;
+    result
+}
+
+/// Device whose `ConnectDevice` call is still pending; disconnected if abandoned.
+struct PendingConnect(Option<String>);
+
+impl Drop for PendingConnect {
+    fn drop(&mut self) {
+        if let Some(device) = self.0.take() {
+            // The blocking call keeps running once its future is dropped, so have BlueZ abort it.
+            disconnect_device(device);
+        }
+    }
+}
+
+/// Disconnects the device in the background, aborting any pending connection attempt.
+pub(crate) fn disconnect_device(object_path: String) {
+    std::thread::spawn(move || {
+        if let Ok(connection) = Connection::new_system() {
+            let proxy = connection.with_proxy(BLUEZ_SERVICE, object_path, Duration::from_secs(5));
+            let _: std::result::Result<(), _> =
+                proxy.method_call(DEVICE_INTERFACE, "Disconnect", ());
+        }
+    });
+}
+
+/// Starts recording `Device1.Disconnected` signals (BlueZ 5.75+) on the shared runtime, unless
+/// already recording.
+pub(crate) fn watch_disconnect_reasons() -> Result<()> {
+    if WATCHING_DISCONNECTS.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+    crate::runtime::handle()?.spawn(async {
+        // Reasons are best effort: without them, disconnects are reported with an unknown reason.
+        let _ = record_disconnect_reasons().await;
+        // Let the next peripheral try again.
+        WATCHING_DISCONNECTS.store(false, Ordering::Release);
+    });
+    Ok(())
+}
+
+/// Records `Device1.Disconnected` signals until the D-Bus connection is lost.
+async fn record_disconnect_reasons() -> std::result::Result<(), dbus::Error> {
+    let (resource, connection) = dbus_tokio::connection::new_system_sync()?;
+    let resource = tokio::spawn(resource);
+    let rule = MatchRule::new_signal(DEVICE_INTERFACE, "Disconnected");
+    let _watch = connection
+        .add_match(rule)
+        .await?
+        .cb(|message, (name, _): (String, String)| {
+            if let Some(path) = message.path() {
+                record_disconnect_reason(path.to_string(), disconnect_reason(&name));
+            }
+            true
+        });
+    let _ = resource.await;
+    Ok(())
+}
+
+fn record_disconnect_reason(object_path: String, reason: DisconnectReason) {
+    let mut reasons = DISCONNECT_REASONS.lock().unwrap();
+    // Drop reasons nobody consumed, e.g. those of devices without a `Peripheral`.
+    reasons.retain(|_, (at, _)| at.elapsed() < DISCONNECT_REASON_TTL);
+    reasons.insert(object_path, (Instant::now(), reason));
+    DISCONNECT_REASON_RECORDED.notify_waiters();
+}
+
+/// Takes the reason BlueZ reported for the device's latest disconnect, if it reported one.
+pub(crate) async fn take_disconnect_reason(object_path: &str) -> Option<DisconnectReason> {
+    let deadline = tokio::time::Instant::now() + DISCONNECT_REASON_GRACE;
+    loop {
+        // Registered before checking, so that a reason recorded in between is not missed.
+        let recorded = DISCONNECT_REASON_RECORDED.notified();
+        if let Some((at, reason)) = DISCONNECT_REASONS.lock().unwrap().remove(object_path) {
+            // Ignore reasons left over from an earlier disconnect that nobody consumed.
+            if at.elapsed() < DISCONNECT_REASON_TTL {
+                return Some(reason);
+            }
+        }
+        if tokio::time::timeout_at(deadline, recorded).await.is_err() {
+            return None;
+        }
+    }
+}
+
+/// Maps the `org.bluez.Reason.*` names onto reasons; codes are those of the kernel management API.
+fn disconnect_reason(name: &str) -> DisconnectReason {
+    match name.strip_prefix("org.bluez.Reason.").unwrap_or(name) {
+        "Local" => DisconnectReason::LocalRequest,
+        "Remote" => DisconnectReason::RemoteUserTerminated,
+        "Timeout" => DisconnectReason::ConnectionTimeout,
+        "Authentication" => DisconnectReason::AuthenticationFailure,
+        "Suspend" => DisconnectReason::AdapterPoweredOff,
+        // BlueZ reports reason names rather than the raw codes.
+        _ => DisconnectReason::Unknown { code: None },
+    }
+}
+
+/// A device's GATT table as BlueZ reports it. Unlike btleplug's, it tells apart characteristics
+/// sharing their service and characteristic UUIDs (e.g. those of two Battery Services), numbering
+/// characteristics by handle.
+#[derive(Default)]
+pub(crate) struct GattTable {
+    pub(crate) services: Vec<Service>,
+    characteristics: HashMap<u32, CharacteristicId>,
+}
+
+impl GattTable {
+    pub(crate) fn characteristic(&self, instance: u32) -> Result<CharacteristicId> {
+        self.characteristics
+            .get(&instance)
+            .cloned()
+            .ok_or(Error::NoSuchCharacteristic)
+    }
+}
+
+/// Reads the GATT table of the device at `object_path`, whose services must have been resolved.
+pub(crate) async fn gatt_table(object_path: &str) -> Result<GattTable> {
+    let session = session().await?;
+    let mut table = GattTable::default();
+    let mut services = session.get_services(&device_id(object_path)?).await?;
+    services.sort_by(|a, b| a.id.cmp(&b.id));
+    for service in services {
+        let mut characteristics = session.get_characteristics(&service.id).await?;
+        characteristics.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut converted = Vec::new();
+        for characteristic in characteristics {
+            let Some(instance) = handle(&characteristic.id) else {
+                continue;
+            };
+            let descriptors = session
+                .get_descriptors(&characteristic.id)
+                .await?
+                .into_iter()
+                .map(|descriptor| Descriptor {
+                    uuid: descriptor.uuid.into(),
+                    service: service.uuid.into(),
+                    characteristic: characteristic.uuid.into(),
+                })
+                .collect();
+            converted.push(Characteristic {
+                uuid: characteristic.uuid.into(),
+                service: service.uuid.into(),
+                instance,
+                properties: CharPropFlags::from(characteristic.flags).into(),
+                descriptors,
+            });
+            table.characteristics.insert(instance, characteristic.id);
+        }
+        table.services.push(Service {
+            uuid: service.uuid.into(),
+            primary: service.primary,
+            characteristics: converted,
+        });
+    }
+    Ok(table)
+}
+
+/// BlueZ names characteristic objects after their handle, e.g. `.../service000a/char000b`.
+fn handle(characteristic: &CharacteristicId) -> Option<u32> {
+    let path = characteristic.to_string();
+    let (_, handle) = path.rsplit_once("/char")?;
+    u32::from_str_radix(handle, 16).ok()
+}
+
+fn device_id(object_path: &str) -> Result<DeviceId> {
+    serde_json::from_value(serde_json::json!({ "object_path": object_path }))
+        .map_err(|e| Error::Other(e.to_string()))
+}
+
+pub(crate) async fn read(characteristic: &CharacteristicId) -> Result<Vec<u8>> {
+    Ok(session()
+        .await?
+        .read_characteristic_value(characteristic)
+        .await?)
+}
+
+pub(crate) async fn write(
+    characteristic: &CharacteristicId,
+    data: &[u8],
+    write_type: WriteType,
+) -> Result<()> {
+    let options = WriteOptions {
+        write_type: Some(match write_type {
+            WriteType::WithResponse => bluez_async::WriteType::WithResponse,
+            WriteType::WithoutResponse => bluez_async::WriteType::WithoutResponse,
+        }),
+        ..WriteOptions::default()
+    };
+    Ok(session()
+        .await?
+        .write_characteristic_value_with_options(characteristic, data, options)
+        .await?)
+}
+
+pub(crate) async fn start_notify(characteristic: &CharacteristicId) -> Result<()> {
+    Ok(session().await?.start_notify(characteristic).await?)
+}
+
+pub(crate) async fn stop_notify(characteristic: &CharacteristicId) -> Result<()> {
+    Ok(session().await?.stop_notify(characteristic).await?)
+}
+
+/// Values of the characteristics of the device at `object_path`, identified by instance.
+pub(crate) async fn notifications(
+    object_path: &str,
+) -> Result<impl Stream<Item = Notification> + Send + Unpin + use<>> {
+    let events = session()
+        .await?
+        .device_event_stream(&device_id(object_path)?)
+        .await?;
+    Ok(Box::pin(events.filter_map(|event| match event {
+        BluetoothEvent::Characteristic {
+            id,
+            event: CharacteristicEvent::Value { value },
+        } => Some(Notification {
+            source: Source::Instance(handle(&id)?),
+            value,
+        }),
+        _ => None,
+    })))
+}
@@ -6,6 +6,11 @@ use btleplug::api::CharPropFlags;
 pub struct Characteristic {
     pub uuid: Uuid,
     pub service: Uuid,
+    /// Identifies the characteristic among those sharing its service and characteristic UUIDs,
+    /// stable for a given GATT database. On Linux, its ATT handle as reported by BlueZ. Elsewhere,
+    /// its position in the GATT table as reported by btleplug, which cannot target one of several
+    /// such characteristics: operations on them fail with [`crate::Error::NotSupported`].
+    pub instance: u32,
     pub properties: CharacteristicPropertyFlags,
     pub descriptors: Vec<Descriptor>,
 }
@@ -26,11 +31,13 @@ impl From<Characteristic> for btleplug::api::Characteristic {
     }
 }
 
-impl From<btleplug::api::Characteristic> for Characteristic {
-    fn from(value: btleplug::api::Characteristic) -> Self {
+impl Characteristic {
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn new(value: btleplug::api::Characteristic, instance: u32) -> Self {
         Self {
             uuid: value.uuid.into(),
             service: value.service_uuid.into(),
+            instance,
             properties: value.properties.into(),
             descriptors: value.descriptors.into_iter().map(Into::into).collect(),
         }
@@ -0,0 +1,297 @@
+/// Class of Device, decoded per the Bluetooth Assigned Numbers (section 2.8).
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct ClassOfDevice {
+    pub major: MajorDeviceClass,
+    /// Raw 6-bit minor device class; its meaning depends on [`ClassOfDevice::major`].
+    pub minor: u8,
+    /// Human-readable description of [`ClassOfDevice::minor`], if defined for the major class.
+    pub minor_description: Option<String>,
+    pub services: Vec<ServiceClass>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, uniffi::Enum)]
+pub enum MajorDeviceClass {
+    Miscellaneous,
+    Computer,
+    Phone,
+    LanNetworkAccessPoint,
+    AudioVideo,
+    Peripheral,
+    Imaging,
+    Wearable,
+    Toy,
+    Health,
+    Uncategorized,
+    Reserved(u8),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, uniffi::Enum)]
+pub enum ServiceClass {
+    LimitedDiscoverableMode,
+    LeAudio,
+    Positioning,
+    Networking,
+    Rendering,
+    Capturing,
+    ObjectTransfer,
+    Audio,
+    Telephony,
+    Information,
+}
+
+const SERVICE_CLASSES: [(u32, ServiceClass); 10] = [
+    (13, ServiceClass::LimitedDiscoverableMode),
+    (14, ServiceClass::LeAudio),
+    (16, ServiceClass::Positioning),
+    (17, ServiceClass::Networking),
+    (18, ServiceClass::Rendering),
+    (19, ServiceClass::Capturing),
+    (20, ServiceClass::ObjectTransfer),
+    (21, ServiceClass::Audio),
+    (22, ServiceClass::Telephony),
+    (23, ServiceClass::Information),
+];
+
+#[uniffi::export]
+pub fn decode_class_of_device(class: u32) -> ClassOfDevice {
+    let minor = ((class >> 2) & 0x3F) as u8;
+    let major = match (class >> 8) & 0x1F {
+        0x00 => MajorDeviceClass::Miscellaneous,
+        0x01 => MajorDeviceClass::Computer,
+        0x02 => MajorDeviceClass::Phone,
+        0x03 => MajorDeviceClass::LanNetworkAccessPoint,
+        0x04 => MajorDeviceClass::AudioVideo,
+        0x05 => MajorDeviceClass::Peripheral,
+        0x06 => MajorDeviceClass::Imaging,
+        0x07 => MajorDeviceClass::Wearable,
+        0x08 => MajorDeviceClass::Toy,
+        0x09 => MajorDeviceClass::Health,
+        0x1F => MajorDeviceClass::Uncategorized,
+        other => MajorDeviceClass::Reserved(other as u8),
+    };
+    let services = SERVICE_CLASSES
+        .iter()
+        .filter(|(bit, _)| class & (1 << bit) != 0)
+        .map(|(_, service)| *service)
+        .collect();
+
+    ClassOfDevice {
+        major,
+        minor,
+        minor_description: minor_description(major, minor),
+        services,
+    }
+}
+
+fn minor_description(major: MajorDeviceClass, minor: u8) -> Option<String> {
+    let name = match major {
+        MajorDeviceClass::Computer => match minor {
+            0 => "Uncategorized",
+            1 => "Desktop workstation",
+            2 => "Server-class computer",
+            3 => "Laptop",
+            4 => "Handheld PC/PDA (clamshell)",
+            5 => "Palm-size PC/PDA",
+            6 => "Wearable computer (watch size)",
+            7 => "Tablet",
+            _ => return None,
+        },
+        MajorDeviceClass::Phone => match minor {
+            0 => "Uncategorized",
+            1 => "Cellular",
+            2 => "Cordless",
+            3 => "Smartphone",
+            4 => "Wired modem or voice gateway",
+            5 => "Common ISDN access",
+            _ => return None,
+        },
+        MajorDeviceClass::LanNetworkAccessPoint => match minor >> 3 {
+            0 => "Fully available",
+            1 => "1% to 17% utilized",
+            2 => "17% to 33% utilized",
+            3 => "33% to 50% utilized",
+            4 => "50% to 67% utilized",
+            5 => "67% to 83% utilized",
+            6 => "83% to 99% utilized",
+            _ => "No service available",
+        },
+        MajorDeviceClass::AudioVideo => match minor {
+            0 => "Uncategorized",
+            1 => "Wearable Headset Device",
+            2 => "Hands-free Device",
+            4 => "Microphone",
+            5 => "Loudspeaker",
+            6 => "Headphones",
+            7 => "Portable Audio",
+            8 => "Car audio",
+            9 => "Set-top box",
+            10 => "HiFi Audio Device",
+            11 => "VCR",
+            12 => "Video Camera",
+            13 => "Camcorder",
+            14 => "Video Monitor",
+            15 => "Video Display and Loudspeaker",
+            16 => "Video Conferencing",
+            18 => "Gaming/Toy",
+            _ => return None,
+        },
+        MajorDeviceClass::Peripheral => return peripheral_description(minor),
+        MajorDeviceClass::Imaging => return imaging_description(minor),
+        MajorDeviceClass::Wearable => match minor {
+            1 => "Wristwatch",
+            2 => "Pager",
+            3 => "Jacket",
+            4 => "Helmet",
+            5 => "Glasses",
+            6 => "Pin",
+            _ => return None,
+        },
+        MajorDeviceClass::Toy => match minor {
+            1 => "Robot",
+            2 => "Vehicle",
+            3 => "Doll/Action figure",
+            4 => "Controller",
+            5 => "Game",
+            _ => return None,
+        },
+        MajorDeviceClass::Health => match minor {
+            0 => "Undefined",
+            1 => "Blood Pressure Monitor",
+            2 => "Thermometer",
+            3 => "Weighing Scale",
+            4 => "Glucose Meter",
+            5 => "Pulse Oximeter",
+            6 => "Heart/Pulse Rate Monitor",
+            7 => "Health Data Display",
+            8 => "Step Counter",
+            9 => "Body Composition Analyzer",
+            10 => "Peak Flow Monitor",
+            11 => "Medication Monitor",
+            12 => "Knee Prosthesis",
+            13 => "Ankle Prosthesis",
+            14 => "Generic Health Manager",
+            15 => "Personal Mobility Device",
+            _ => return None,
+        },
+        MajorDeviceClass::Miscellaneous
+        | MajorDeviceClass::Uncategorized
+        | MajorDeviceClass::Reserved(_) => return None,
+    };
+    Some(name.to_string())
+}
+
+/// Peripheral minor classes combine keyboard/pointing flags (upper two bits) with a device type.
+fn peripheral_description(minor: u8) -> Option<String> {
+    let input = match minor >> 4 {
+        1 => Some("Keyboard"),
+        2 => Some("Pointing device"),
+        3 => Some("Combo keyboard/pointing device"),
+        _ => None,
+    };
+    let kind = match minor & 0x0F {
+        0 => None,
+        1 => Some("Joystick"),
+        2 => Some("Gamepad"),
+        3 => Some("Remote control"),
+        4 => Some("Sensing device"),
+        5 => Some("Digitizer tablet"),
+        6 => Some("Card reader"),
+        7 => Some("Digital pen"),
+        8 => Some("Handheld scanner"),
+        9 => Some("Handheld gestural input device"),
+        _ => return None,
+    };
+    match (input, kind) {
+        (None, None) => Some("Uncategorized".to_string()),
+        (input, kind) => Some(input.into_iter().chain(kind).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+/// Imaging minor classes are a set of flags rather than an enumeration.
+fn imaging_description(minor: u8) -> Option<String> {
+    let kinds: Vec<_> = [
+        (0x04, "Display"),
+        (0x08, "Camera"),
+        (0x10, "Scanner"),
+        (0x20, "Printer"),
+    ]
+    .into_iter()
+    .filter(|(bit, _)| minor & bit != 0)
+    .map(|(_, name)| name)
+    .collect();
+    (!kinds.is_empty()).then(|| kinds.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headset() {
+        let class = decode_class_of_device(0x240404);
+        assert_eq!(class.major, MajorDeviceClass::AudioVideo);
+        assert_eq!(class.minor, 1);
+        assert_eq!(
+            class.minor_description.as_deref(),
+            Some("Wearable Headset Device")
+        );
+        assert_eq!(
+            class.services,
+            [ServiceClass::Rendering, ServiceClass::Audio]
+        );
+    }
+
+    #[test]
+    fn smartphone() {
+        let class = decode_class_of_device(0x5A020C);
+        assert_eq!(class.major, MajorDeviceClass::Phone);
+        assert_eq!(class.minor_description.as_deref(), Some("Smartphone"));
+        assert_eq!(
+            class.services,
+            [
+                ServiceClass::Networking,
+                ServiceClass::Capturing,
+                ServiceClass::ObjectTransfer,
+                ServiceClass::Telephony,
+            ]
+        );
+    }
+
+    #[test]
+    fn peripheral_combines_input_flags_and_device_type() {
+        let description = |class| decode_class_of_device(class).minor_description;
+        assert_eq!(description(0x0540).as_deref(), Some("Keyboard"));
+        assert_eq!(
+            description(0x05CC).as_deref(),
+            Some("Combo keyboard/pointing device, Remote control")
+        );
+        assert_eq!(description(0x0508).as_deref(), Some("Gamepad"));
+        assert_eq!(description(0x0500).as_deref(), Some("Uncategorized"));
+    }
+
+    #[test]
+    fn imaging_lists_every_flag() {
+        let description = |class| decode_class_of_device(class).minor_description;
+        assert_eq!(description(0x0680).as_deref(), Some("Printer"));
+        assert_eq!(description(0x06C0).as_deref(), Some("Scanner, Printer"));
+        assert_eq!(description(0x0630).as_deref(), Some("Display, Camera"));
+        assert_eq!(description(0x0600), None);
+    }
+
+    #[test]
+    fn reserved_major_class() {
+        let class = decode_class_of_device(0x0A04);
+        assert_eq!(class.major, MajorDeviceClass::Reserved(0x0A));
+        assert_eq!(class.minor, 1);
+        assert_eq!(class.minor_description, None);
+        assert!(class.services.is_empty());
+    }
+
+    #[test]
+    fn uncategorized_major_class() {
+        assert_eq!(
+            decode_class_of_device(0x1F00).major,
+            MajorDeviceClass::Uncategorized
+        );
+    }
+}
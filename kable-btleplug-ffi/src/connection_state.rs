@@ -0,0 +1,15 @@
+use crate::disconnect_reason::DisconnectReason;
+
+#[derive(Clone, Debug, Eq, PartialEq, uniffi::Enum)]
+pub enum ConnectionState {
+    /// `reason` is absent until the first connection attempt ends.
+    Disconnected {
+        reason: Option<DisconnectReason>,
+    },
+    /// The adapter no longer knows the device; scanning until it is seen again.
+    ScanningForDevice,
+    Connecting,
+    DiscoveringServices,
+    Ready,
+    Disconnecting,
+}
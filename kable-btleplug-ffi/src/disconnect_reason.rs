@@ -0,0 +1,16 @@
+#[derive(Clone, Debug, Eq, PartialEq, uniffi::Enum)]
+pub enum DisconnectReason {
+    /// Disconnect was requested through [`crate::peripheral::Peripheral`] (or by this host).
+    LocalRequest,
+    RemoteUserTerminated,
+    /// Link supervision timeout, e.g. the device went out of range.
+    ConnectionTimeout,
+    AdapterPoweredOff,
+    AuthenticationFailure,
+    /// The connection attempt failed (or timed out) before the link was ready.
+    ConnectionFailed,
+    /// `code` is the platform's raw reason code, when it reports one.
+    Unknown {
+        code: Option<u8>,
+    },
+}
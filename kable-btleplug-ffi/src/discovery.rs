@@ -0,0 +1,120 @@
+use crate::{Result, get_adapter};
+use btleplug::api::{Central, ScanFilter};
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Live sessions and the filter the adapter currently scans with. The adapter scans while any
+/// session is alive, so that user scans (see `scan`) and connection rescans (see
+/// `Peripheral::connect`) do not stop each other's discovery.
+static SESSIONS: Mutex<Sessions> = Mutex::const_new(Sessions {
+    next_id: 0,
+    services: BTreeMap::new(),
+    scanning: None,
+});
+
+struct Sessions {
+    next_id: u64,
+    /// Services each session is interested in (empty for all devices), by session ID.
+    services: BTreeMap<u64, Vec<Uuid>>,
+    scanning: Option<ScanFilter>,
+}
+
+impl Sessions {
+    /// The narrowest filter that still reports every device some session is interested in.
+    fn filter(&self) -> Option<ScanFilter> {
+        if self.services.is_empty() {
+            return None;
+        }
+        if self.services.values().any(Vec::is_empty) {
+            return Some(ScanFilter::default());
+        }
+        let mut services: Vec<_> = self.services.values().flatten().copied().collect();
+        services.sort();
+        services.dedup();
+        Some(ScanFilter { services })
+    }
+
+    /// Starts, stops or re-filters the scan to match the live sessions.
+    async fn apply(&mut self) -> Result<()> {
+        let filter = self.filter();
+        if filter == self.scanning {
+            return Ok(());
+        }
+        let adapter = get_adapter().await?;
+        if self.scanning.take().is_some() {
+            // May fail if the adapter is powered off (e.g. computer went to sleep); ignore, as
+            // scanning has already stopped in that case.
+            let _ = adapter.stop_scan().await;
+        }
+        if let Some(filter) = filter {
+            adapter.start_scan(filter.clone()).await?;
+            self.scanning = Some(filter);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the adapter scanning for as long as it is alive. Prefer [`DiscoverySession::stop`], as
+/// dropping can only schedule the stop on the current runtime.
+pub(crate) struct DiscoverySession {
+    id: u64,
+    active: bool,
+}
+
+impl DiscoverySession {
+    /// Scans for all devices.
+    pub(crate) async fn start() -> Result<Self> {
+        Self::targeted(Vec::new()).await
+    }
+
+    /// Scans only for devices advertising any of `services`. Platforms cannot filter scans by
+    /// device, so a session for a single device is at best narrowed to the services it advertised;
+    /// without any (e.g. a device never seen advertising), the scan is not filtered at all. While
+    /// other sessions are alive the adapter scans with the union of their filters, so devices
+    /// outside `services` may be reported too.
+    pub(crate) async fn targeted(services: Vec<Uuid>) -> Result<Self> {
+        let mut sessions = SESSIONS.lock().await;
+        let id = sessions.next_id;
+        sessions.next_id += 1;
+        sessions.services.insert(id, services);
+        if let Err(e) = sessions.apply().await {
+            sessions.services.remove(&id);
+            return Err(e);
+        }
+        Ok(Self { id, active: true })
+    }
+
+    /// Re-issues the scan after the system stopped it (e.g. adapter powered off and back on).
+    pub(crate) async fn restart(&self) {
+        let sessions = SESSIONS.lock().await;
+        if let Some(filter) = sessions.scanning.clone()
+            && let Ok(adapter) = get_adapter().await
+        {
+            let _ = adapter.start_scan(filter).await;
+        }
+    }
+
+    pub(crate) async fn stop(mut self) {
+        self.active = false;
+        release(self.id).await;
+    }
+}
+
+impl Drop for DiscoverySession {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(release(self.id));
+        }
+    }
+}
+
+async fn release(id: u64) {
+    let mut sessions = SESSIONS.lock().await;
+    sessions.services.remove(&id);
+    // Best effort: the adapter may be powered off, in which case scanning has stopped anyway.
+    let _ = sessions.apply().await;
+}
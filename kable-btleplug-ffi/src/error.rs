@@ -35,6 +35,33 @@ pub enum Error {
     #[error("Invalid Bluetooth address: {0}")]
     InvalidBDAddr(String),
 
+    #[error("Device is blocked by the access list")]
+    Blocked,
+
+    #[error("Bluetooth adapter is not ready (e.g. powered off)")]
+    AdapterNotReady,
+
+    #[error("A connection attempt is already in progress")]
+    ConnectionInProgress,
+
+    #[error("Connection aborted: {}", _0)]
+    ConnectionAborted(String),
+
+    #[error("Connection refused: {}", _0)]
+    ConnectionRefused(String),
+
+    #[error("Connection limit reached")]
+    ConnectionLimitReached,
+
+    #[error("Authentication failed: {}", _0)]
+    AuthenticationFailed(String),
+
+    #[error("Failed to set up notifications: {}", _0)]
+    NotificationSetupFailed(String),
+
+    #[error("Connection failed: {}", _0)]
+    ConnectionFailed(String),
+
     #[error("Runtime Error: {}", _0)]
     RuntimeError(String),
 
@@ -60,3 +87,62 @@ impl From<btleplug::Error> for Error {
         }
     }
 }
+
+impl Error {
+    /// Refines an error from a connection attempt into the reason the connection failed.
+    pub(crate) fn from_connect(value: btleplug::Error) -> Self {
+        match Self::from(value) {
+            Self::Other(message) => connection_failure(&message),
+            other => other,
+        }
+    }
+}
+
+/// btleplug only preserves the message of platform errors. BlueZ reports connection failure reasons
+/// as the D-Bus error message (e.g. `le-connection-abort-by-local`), so classify by message.
+fn connection_failure(message: &str) -> Error {
+    let lowercase = message.to_lowercase();
+    let contains = |patterns: &[&str]| patterns.iter().any(|p| lowercase.contains(p));
+    if contains(&["not ready", "not powered"]) {
+        Error::AdapterNotReady
+    } else if contains(&["in progress"]) {
+        Error::ConnectionInProgress
+    } else if contains(&["concurrent-connection-limit", "connection limit"]) {
+        Error::ConnectionLimitReached
+    } else if contains(&["abort", "canceled", "cancelled"]) {
+        Error::ConnectionAborted(message.to_string())
+    } else if contains(&["refused", "profile-unavailable", "not-supported"]) {
+        Error::ConnectionRefused(message.to_string())
+    } else if contains(&["authentication"]) {
+        Error::AuthenticationFailed(message.to_string())
+    } else {
+        Error::ConnectionFailed(message.to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<dbus::Error> for Error {
+    fn from(value: dbus::Error) -> Self {
+        let message = value.message().unwrap_or_default();
+        match value.name() {
+            Some("org.bluez.Error.NotSupported" | "org.freedesktop.DBus.Error.UnknownMethod") => {
+                Self::NotSupported(value.to_string())
+            }
+            Some("org.bluez.Error.NotReady") => Self::AdapterNotReady,
+            Some("org.bluez.Error.InProgress") => Self::ConnectionInProgress,
+            Some("org.bluez.Error.AuthenticationFailed") => {
+                Self::AuthenticationFailed(message.to_string())
+            }
+            Some("org.bluez.Error.Failed") => connection_failure(message),
+            _ => Self::Other(value.to_string()),
+        }
+    }
+}
+
+/// Reported as btleplug reports the errors of the same calls.
+#[cfg(target_os = "linux")]
+impl From<bluez_async::BluetoothError> for Error {
+    fn from(value: bluez_async::BluetoothError) -> Self {
+        Self::Other(value.to_string())
+    }
+}
@@ -0,0 +1,153 @@
+//! Single adapter event pump, routing [`CentralEvent`]s to listeners by peripheral ID so that the
+//! adapter's event stream is subscribed to once, regardless of the number of peripherals.
+
+use crate::{Result, get_adapter, runtime};
+use btleplug::api::{Central, CentralEvent};
+use btleplug::platform::PeripheralId;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio_stream::{Stream, StreamExt};
+
+type Listeners = HashMap<u64, UnboundedSender<CentralEvent>>;
+type Listener = (Registration, UnboundedReceiver<CentralEvent>);
+type Events = Pin<Box<dyn Stream<Item = CentralEvent> + Send>>;
+
+/// Listeners for the events of a single peripheral.
+static DEVICE_LISTENERS: LazyLock<Mutex<HashMap<PeripheralId, Listeners>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// Listeners for every event (e.g. scans).
+static GLOBAL_LISTENERS: LazyLock<Mutex<Listeners>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_KEY: AtomicU64 = AtomicU64::new(0);
+/// Whether the pump is running. Once started, it runs for the life of the process, resubscribing
+/// whenever the adapter's event stream ends so that existing listeners keep receiving events.
+static PUMP_STARTED: tokio::sync::Mutex<bool> = tokio::sync::Mutex::const_new(false);
+/// Delay before resubscribing to the adapter's events, doubled after each failure.
+const RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Unregisters its listener when dropped.
+pub(crate) struct Registration {
+    id: Option<PeripheralId>,
+    key: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        match &self.id {
+            None => {
+                GLOBAL_LISTENERS.lock().unwrap().remove(&self.key);
+            }
+            Some(id) => {
+                let mut listeners = DEVICE_LISTENERS.lock().unwrap();
+                if let Some(device) = listeners.get_mut(id) {
+                    device.remove(&self.key);
+                    if device.is_empty() {
+                        listeners.remove(id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Receives the events concerning the given peripheral, as well as adapter state updates.
+pub(crate) async fn register(id: &PeripheralId) -> Result<Listener> {
+    start_pump().await?;
+    let (sender, receiver) = unbounded_channel();
+    let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+    DEVICE_LISTENERS
+        .lock()
+        .unwrap()
+        .entry(id.clone())
+        .or_default()
+        .insert(key, sender);
+    let registration = Registration {
+        id: Some(id.clone()),
+        key,
+    };
+    Ok((registration, receiver))
+}
+
+/// Receives every adapter event.
+pub(crate) async fn register_global() -> Result<Listener> {
+    start_pump().await?;
+    let (sender, receiver) = unbounded_channel();
+    let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+    GLOBAL_LISTENERS.lock().unwrap().insert(key, sender);
+    Ok((Registration { id: None, key }, receiver))
+}
+
+async fn start_pump() -> Result<()> {
+    let mut started = PUMP_STARTED.lock().await;
+    if !*started {
+        let events = subscribe().await?;
+        runtime::handle()?.spawn(pump(events));
+        *started = true;
+    }
+    Ok(())
+}
+
+async fn subscribe() -> Result<Events> {
+    Ok(get_adapter().await?.events().await?)
+}
+
+async fn pump(mut events: Events) {
+    loop {
+        while let Some(event) = events.next().await {
+            dispatch(event);
+        }
+        events = resubscribe().await;
+    }
+}
+
+/// Resubscribes after the adapter's event stream ended (e.g. the D-Bus connection was lost).
+/// Events emitted in the meantime are missed.
+async fn resubscribe() -> Events {
+    let mut backoff = RESUBSCRIBE_BACKOFF;
+    loop {
+        tokio::time::sleep(backoff).await;
+        if let Ok(events) = subscribe().await {
+            return events;
+        }
+        backoff = (backoff * 2).min(MAX_RESUBSCRIBE_BACKOFF);
+    }
+}
+
+fn dispatch(event: CentralEvent) {
+    GLOBAL_LISTENERS
+        .lock()
+        .unwrap()
+        .retain(|_, listener| listener.send(event.clone()).is_ok());
+    let mut devices = DEVICE_LISTENERS.lock().unwrap();
+    match peripheral_id(&event) {
+        Some(id) => {
+            if let Some(listeners) = devices.get_mut(id) {
+                listeners.retain(|_, listener| listener.send(event.clone()).is_ok());
+            }
+        }
+        // Adapter state concerns every peripheral.
+        None if matches!(event, CentralEvent::StateUpdate(_)) => {
+            for listeners in devices.values_mut() {
+                listeners.retain(|_, listener| listener.send(event.clone()).is_ok());
+            }
+        }
+        None => {}
+    }
+}
+
+fn peripheral_id(event: &CentralEvent) -> Option<&PeripheralId> {
+    match event {
+        CentralEvent::DeviceDiscovered(id)
+        | CentralEvent::DeviceUpdated(id)
+        | CentralEvent::DeviceConnected(id)
+        | CentralEvent::DeviceDisconnected(id)
+        | CentralEvent::ManufacturerDataAdvertisement { id, .. }
+        | CentralEvent::ServiceDataAdvertisement { id, .. }
+        | CentralEvent::ServicesAdvertisement { id, .. } => Some(id),
+        _ => None,
+    }
+}
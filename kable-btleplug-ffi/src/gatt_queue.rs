@@ -0,0 +1,356 @@
+use crate::cancellation_handle::CancellationHandle;
+use crate::{Error, Result};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, uniffi::Enum)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Copy, Clone, Debug, Default, uniffi::Record)]
+pub struct OperationOptions {
+    pub priority: Priority,
+    /// Covers both waiting for earlier operations and the operation itself; `None` uses the
+    /// peripheral's default (see `Peripheral::set_operation_timeout`).
+    pub timeout: Option<Duration>,
+}
+
+/// Serializes a peripheral's GATT operations, highest priority first and in order of submission
+/// within a priority.
+pub(crate) struct GattQueue {
+    state: Mutex<State>,
+    default_timeout: Mutex<Duration>,
+}
+
+#[derive(Default)]
+struct State {
+    busy: bool,
+    next_sequence: u64,
+    waiting: BinaryHeap<Waiter>,
+}
+
+struct Waiter {
+    priority: Priority,
+    sequence: u64,
+    turn: oneshot::Sender<()>,
+}
+
+impl Waiter {
+    fn key(&self) -> (Priority, Reverse<u64>) {
+        (self.priority, Reverse(self.sequence))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Default for GattQueue {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            default_timeout: Mutex::new(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+impl GattQueue {
+    pub(crate) fn set_default_timeout(&self, timeout: Duration) {
+        *self.default_timeout.lock().unwrap() = timeout;
+    }
+
+    /// Runs `operation` once it is its turn, failing with [`Error::TimedOut`] if it does not
+    /// complete in time, or with [`Error::Cancelled`] (leaving the queue if still waiting) once
+    /// `cancellation` is cancelled. Dropping the returned future (as uniffi does when the calling
+    /// coroutine is cancelled) likewise leaves the queue.
+    pub(crate) async fn run<T>(
+        &self,
+        options: Option<OperationOptions>,
+        cancellation: Option<Arc<CancellationHandle>>,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let token = cancellation
+            .map(|handle| handle.token())
+            .unwrap_or_default();
+        let options = options.unwrap_or_default();
+        let duration = options
+            .timeout
+            .unwrap_or_else(|| *self.default_timeout.lock().unwrap());
+        let run = async {
+            let _turn = self.acquire(options.priority).await;
+            operation.await
+        };
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(Error::Cancelled),
+            result = timeout(duration, run) => result.unwrap_or(Err(Error::TimedOut(duration))),
+        }
+    }
+
+    async fn acquire(&self, priority: Priority) -> Turn<'_> {
+        let mut waiting = {
+            let mut state = self.state.lock().unwrap();
+            if !state.busy {
+                state.busy = true;
+                return Turn { queue: self };
+            }
+            let (turn, receiver) = oneshot::channel();
+            let sequence = state.next_sequence;
+            state.next_sequence += 1;
+            state.waiting.push(Waiter {
+                priority,
+                sequence,
+                turn,
+            });
+            Waiting {
+                queue: self,
+                sequence,
+                receiver,
+            }
+        };
+        // Cannot fail: waiters are only dropped once handed their turn.
+        let _ = (&mut waiting.receiver).await;
+        Turn { queue: self }
+    }
+
+    /// Hands the turn to the next waiter still waiting, if any.
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(waiter) = state.waiting.pop() {
+            if waiter.turn.send(()).is_ok() {
+                return;
+            }
+        }
+        state.busy = false;
+    }
+}
+
+/// Releases the queue when dropped.
+struct Turn<'a> {
+    queue: &'a GattQueue,
+}
+
+impl Drop for Turn<'_> {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+/// Leaves the queue when the waiting operation is abandoned (e.g. timed out or cancelled), passing
+/// the turn on if it was handed over in the meantime.
+struct Waiting<'a> {
+    queue: &'a GattQueue,
+    sequence: u64,
+    receiver: oneshot::Receiver<()>,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.queue
+            .state
+            .lock()
+            .unwrap()
+            .waiting
+            .retain(|waiter| waiter.sequence != self.sequence);
+        self.receiver.close();
+        if self.receiver.try_recv().is_ok() {
+            self.queue.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+    use tokio::task::JoinHandle;
+
+    /// Occupies the queue until the returned sender is dropped.
+    async fn occupy(queue: &Arc<GattQueue>) -> (oneshot::Sender<()>, JoinHandle<Result<()>>) {
+        let (release, released) = oneshot::channel::<()>();
+        let operation = {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                queue
+                    .run(None, None, async {
+                        let _ = released.await;
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        wait_until(|| queue.state.lock().unwrap().busy).await;
+        (release, operation)
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        timeout(Duration::from_secs(1), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    fn waiting(queue: &GattQueue) -> usize {
+        queue.state.lock().unwrap().waiting.len()
+    }
+
+    fn options(priority: Priority, timeout: Duration) -> Option<OperationOptions> {
+        Some(OperationOptions {
+            priority,
+            timeout: Some(timeout),
+        })
+    }
+
+    #[tokio::test]
+    async fn operations_run_by_priority_then_in_order_of_submission() {
+        let queue = Arc::new(GattQueue::default());
+        let (release, first) = occupy(&queue).await;
+        let (ran, mut order) = unbounded_channel();
+        let mut operations = Vec::new();
+        for (name, priority) in [
+            ("low", Priority::Low),
+            ("normal 1", Priority::Normal),
+            ("high", Priority::High),
+            ("normal 2", Priority::Normal),
+        ] {
+            let (submitting, ran) = (queue.clone(), ran.clone());
+            operations.push(tokio::spawn(async move {
+                submitting
+                    .run(
+                        Some(OperationOptions {
+                            priority,
+                            timeout: None,
+                        }),
+                        None,
+                        async {
+                            let _ = ran.send(name);
+                            Ok(())
+                        },
+                    )
+                    .await
+            }));
+            let submitted = operations.len();
+            wait_until(|| waiting(&queue) == submitted).await;
+        }
+
+        drop(release);
+        first.await.unwrap().unwrap();
+        for operation in operations {
+            operation.await.unwrap().unwrap();
+        }
+        drop(ran);
+        let mut names = Vec::new();
+        while let Some(name) = order.recv().await {
+            names.push(name);
+        }
+        assert_eq!(names, ["high", "normal 1", "normal 2", "low"]);
+    }
+
+    #[tokio::test]
+    async fn stalled_operation_times_out() {
+        let queue = GattQueue::default();
+        let duration = Duration::from_millis(50);
+        let result = queue
+            .run(
+                options(Priority::Normal, duration),
+                None,
+                std::future::pending::<Result<()>>(),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::TimedOut(d)) if d == duration));
+        // The turn is passed on.
+        assert!(!queue.state.lock().unwrap().busy);
+    }
+
+    #[tokio::test]
+    async fn timeout_covers_waiting_for_earlier_operations() {
+        let queue = Arc::new(GattQueue::default());
+        let (release, first) = occupy(&queue).await;
+        let duration = Duration::from_millis(50);
+        let result = queue
+            .run(options(Priority::High, duration), None, async { Ok(()) })
+            .await;
+        assert!(matches!(result, Err(Error::TimedOut(d)) if d == duration));
+        assert_eq!(waiting(&queue), 0);
+
+        drop(release);
+        first.await.unwrap().unwrap();
+        assert!(!queue.state.lock().unwrap().busy);
+    }
+
+    #[tokio::test]
+    async fn dropped_waiter_leaves_the_queue() {
+        let queue = Arc::new(GattQueue::default());
+        let (release, first) = occupy(&queue).await;
+        let waiter = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.run(None, None, async { Ok(()) }).await })
+        };
+        wait_until(|| waiting(&queue) == 1).await;
+        waiter.abort();
+        assert!(waiter.await.unwrap_err().is_cancelled());
+        assert_eq!(waiting(&queue), 0);
+
+        drop(release);
+        first.await.unwrap().unwrap();
+        assert!(!queue.state.lock().unwrap().busy);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_is_removed_before_it_runs() {
+        let queue = Arc::new(GattQueue::default());
+        let (release, first) = occupy(&queue).await;
+        let cancellation = Arc::new(CancellationHandle::new());
+        let (ran, mut runs) = unbounded_channel();
+        let waiter = {
+            let (queue, cancellation) = (queue.clone(), cancellation.clone());
+            tokio::spawn(async move {
+                queue
+                    .run(None, Some(cancellation), async {
+                        let _ = ran.send(());
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        wait_until(|| waiting(&queue) == 1).await;
+        cancellation.cancel();
+        assert!(matches!(waiter.await.unwrap(), Err(Error::Cancelled)));
+        assert_eq!(waiting(&queue), 0);
+
+        drop(release);
+        first.await.unwrap().unwrap();
+        assert!(!queue.state.lock().unwrap().busy);
+        assert!(runs.recv().await.is_none());
+    }
+}
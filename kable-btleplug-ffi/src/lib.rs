@@ -5,15 +5,34 @@ use tokio::sync::OnceCell;
 
 uniffi::setup_scaffolding!();
 
+pub mod access_list;
+pub mod address_type;
+pub mod assigned_numbers;
+#[cfg(target_os = "linux")]
+mod bluez;
 pub mod cancellation_handle;
 pub mod characteristic;
+pub mod class_of_device;
+pub mod connection_state;
 pub mod descriptor;
+pub mod disconnect_reason;
+mod discovery;
 pub mod error;
+mod events;
+pub mod gatt_queue;
+pub mod notification_batch;
+mod notification_pump;
+pub mod notification_queue;
+pub mod observation;
 pub mod peripheral;
 pub mod peripheral_id;
 pub mod peripheral_properties;
+pub mod reconnect;
+pub mod registry;
+pub mod runtime;
 pub mod scan;
 pub mod service;
+pub mod subscription_mode;
 pub mod uuid;
 pub mod write_type;
 
@@ -22,26 +41,33 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 static ADAPTER: OnceCell<Adapter> = OnceCell::const_new();
 
-async fn get_adapter() -> Adapter {
-    ADAPTER.get_or_init(create_adapter).await.clone()
+async fn get_adapter() -> Result<Adapter> {
+    ADAPTER.get_or_try_init(create_adapter).await.cloned()
 }
 
-async fn create_adapter() -> Adapter {
-    Manager::new()
-        .await
-        .unwrap()
-        .adapters()
+/// The adapter is created on the shared runtime, as btleplug ties its background tasks (e.g. the
+/// D-Bus connection on Linux) to the runtime it is created on.
+async fn create_adapter() -> Result<Adapter> {
+    runtime::handle()?
+        .spawn(async {
+            Manager::new()
+                .await?
+                .adapters()
+                .await?
+                .into_iter()
+                .next()
+                .ok_or(Error::NotSupported("Unable to get adapter".to_string()))
+        })
         .await
-        .unwrap()
-        .first()
-        .unwrap()
-        .clone()
+        .map_err(|e| Error::RuntimeError(e.to_string()))?
 }
 
 #[uniffi::export(async_runtime = "tokio")]
 async fn is_adapter_on() -> bool {
-    get_adapter()
-        .await
+    let Ok(adapter) = get_adapter().await else {
+        return false;
+    };
+    adapter
         .adapter_state()
         .await
         .map_or_else(|_| false, |state| state == CentralState::PoweredOn)
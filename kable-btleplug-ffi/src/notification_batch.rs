@@ -0,0 +1,28 @@
+use std::sync::LazyLock;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Origin of [`TimestampedValue::monotonic`].
+static EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Opt-in coalescing of a characteristic's notifications: values are delivered together once
+/// `interval` has elapsed since the first value of the batch, or once `max_items` are gathered.
+#[derive(Copy, Clone, Debug, uniffi::Record)]
+pub struct NotificationBatchConfig {
+    pub interval: Duration,
+    pub max_items: u32,
+}
+
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct TimestampedValue {
+    /// Wall-clock time at which the notification was received from the platform.
+    pub timestamp: SystemTime,
+    /// Monotonic time at which the notification was received from the platform, relative to an
+    /// arbitrary point fixed for the lifetime of the process. Unaffected by clock adjustments, so
+    /// suitable for measuring the time between notifications.
+    pub monotonic: Duration,
+    pub data: Vec<u8>,
+}
+
+pub(crate) fn monotonic_now() -> Duration {
+    EPOCH.elapsed()
+}
@@ -0,0 +1,459 @@
+use crate::notification_batch::{NotificationBatchConfig, TimestampedValue, monotonic_now};
+use crate::notification_queue::{NotificationQueue, NotificationQueueConfig};
+use crate::{Result, runtime};
+use btleplug::api::ValueNotification;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::time::{Instant, timeout_at};
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+/// A characteristic value, as reported by the platform.
+pub(crate) struct Notification {
+    pub(crate) source: Source,
+    pub(crate) value: Vec<u8>,
+}
+
+impl From<ValueNotification> for Notification {
+    fn from(notification: ValueNotification) -> Self {
+        Self {
+            source: Source::Uuids(notification.service_uuid, notification.uuid),
+            value: notification.value,
+        }
+    }
+}
+
+/// A notification, as received from the platform.
+pub(crate) struct Received {
+    pub(crate) notification: Notification,
+    pub(crate) timestamp: SystemTime,
+    pub(crate) monotonic: Duration,
+}
+
+impl From<Received> for TimestampedValue {
+    fn from(received: Received) -> Self {
+        Self {
+            timestamp: received.timestamp,
+            monotonic: received.monotonic,
+            data: received.notification.value,
+        }
+    }
+}
+
+/// A notification's characteristic, as precisely as the platform identifies it.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Source {
+    /// Service and characteristic UUIDs, which characteristics may share.
+    Uuids(uuid::Uuid, uuid::Uuid),
+    /// See [`crate::characteristic::Characteristic::instance`].
+    Instance(u32),
+}
+
+pub(crate) enum Delivery {
+    Single(Received),
+    /// Notifications of the given characteristic, in order of arrival.
+    Batch(Source, Vec<Received>),
+}
+
+/// Forwards a connection's notifications. At most one pump runs at a time: starting one (on each
+/// connect) stops the previous, and it is stopped on disconnect, adapter loss or when its parent
+/// token (the peripheral's) is cancelled.
+///
+/// Notifications are read from the platform into a bounded queue, so that a slow consumer does not
+/// stall the platform's stream (see [`NotificationQueueConfig`]), and optionally coalesced (see
+/// [`NotificationBatchConfig`]).
+#[derive(Default)]
+pub(crate) struct NotificationPump {
+    current: Mutex<Option<CancellationToken>>,
+    /// Applies from the next connection on.
+    config: Mutex<NotificationQueueConfig>,
+    /// Applies from the next connection on.
+    batching: Mutex<Option<NotificationBatchConfig>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl NotificationPump {
+    pub(crate) fn start<S, F, Fut>(
+        &self,
+        parent: &CancellationToken,
+        mut notifications: S,
+        deliver: F,
+    ) -> Result<()>
+    where
+        S: Stream<Item = Notification> + Send + Unpin + 'static,
+        F: Fn(Delivery) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let token = parent.child_token();
+        if let Some(previous) = self.current.lock().unwrap().replace(token.clone()) {
+            previous.cancel();
+        }
+        let queue = Arc::new(NotificationQueue::new(*self.config.lock().unwrap()));
+        let batching = *self.batching.lock().unwrap();
+        let dropped = self.dropped.clone();
+        let fill = {
+            let queue = queue.clone();
+            async move {
+                while let Some(notification) = notifications.next().await {
+                    let received = Received {
+                        notification,
+                        timestamp: SystemTime::now(),
+                        monotonic: monotonic_now(),
+                    };
+                    queue.push(received, &dropped).await;
+                }
+            }
+        };
+        let drain = async move {
+            match batching {
+                None => loop {
+                    deliver(Delivery::Single(queue.pop().await)).await;
+                },
+                Some(config) => drain_batches(&queue, config, deliver).await,
+            }
+        };
+        runtime::spawn(token, async move {
+            // Queued notifications are discarded once the stream ends, as the connection is gone.
+            tokio::select! {
+                _ = fill => {}
+                _ = drain => {}
+            }
+        })
+    }
+
+    pub(crate) fn stop(&self) {
+        if let Some(current) = self.current.lock().unwrap().take() {
+            current.cancel();
+        }
+    }
+
+    pub(crate) fn configure(&self, config: NotificationQueueConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    pub(crate) fn configure_batching(&self, config: Option<NotificationBatchConfig>) {
+        *self.batching.lock().unwrap() = config;
+    }
+
+    /// Notifications dropped because the queue was full, across connections.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+struct Batch {
+    deadline: Instant,
+    items: Vec<Received>,
+}
+
+async fn drain_batches<F, Fut>(
+    queue: &NotificationQueue<Received>,
+    config: NotificationBatchConfig,
+    deliver: F,
+) where
+    F: Fn(Delivery) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let max_items = (config.max_items as usize).max(1);
+    let mut batches: HashMap<Source, Batch> = HashMap::new();
+    loop {
+        let deadline = batches.values().map(|batch| batch.deadline).min();
+        let received = match deadline {
+            None => Some(queue.pop().await),
+            Some(deadline) => timeout_at(deadline, queue.pop()).await.ok(),
+        };
+        if let Some(received) = received {
+            let source = received.notification.source;
+            let batch = batches.entry(source).or_insert_with(|| Batch {
+                deadline: Instant::now() + config.interval,
+                items: Vec::new(),
+            });
+            batch.items.push(received);
+            if batch.items.len() >= max_items {
+                let batch = batches.remove(&source).unwrap();
+                deliver(Delivery::Batch(source, batch.items)).await;
+            }
+        }
+
+        let now = Instant::now();
+        let due: Vec<_> = batches
+            .iter()
+            .filter(|(_, batch)| batch.deadline <= now)
+            .map(|(source, _)| *source)
+            .collect();
+        for source in due {
+            let batch = batches.remove(&source).unwrap();
+            deliver(Delivery::Batch(source, batch.items)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification_queue::OverflowPolicy;
+    use tokio::sync::Semaphore;
+    use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+    use tokio::time::timeout;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    /// Starts a pump for a new "connection", returning the sender feeding its notifications.
+    fn connect(
+        pump: &NotificationPump,
+        parent: &CancellationToken,
+        delivered: &UnboundedSender<Vec<u8>>,
+    ) -> UnboundedSender<Notification> {
+        let (sender, receiver) = unbounded_channel();
+        let delivered = delivered.clone();
+        pump.start(
+            parent,
+            UnboundedReceiverStream::new(receiver),
+            move |delivery| {
+                let _ = delivered.send(single(delivery).value);
+                async {}
+            },
+        )
+        .unwrap();
+        sender
+    }
+
+    fn single(delivery: Delivery) -> Notification {
+        match delivery {
+            Delivery::Single(received) => received.notification,
+            Delivery::Batch(..) => panic!("Unexpected batch"),
+        }
+    }
+
+    fn notification(value: u8) -> Notification {
+        Notification {
+            source: Source::Instance(1),
+            value: vec![value],
+        }
+    }
+
+    async fn assert_delivered_only(delivered: &mut UnboundedReceiver<Vec<u8>>, expected: &[u8]) {
+        for value in expected {
+            let received = timeout(Duration::from_secs(1), delivered.recv()).await;
+            assert_eq!(received.ok().flatten(), Some(vec![*value]));
+        }
+        assert!(
+            timeout(Duration::from_millis(100), delivered.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn no_duplicate_delivery_after_reconnects() {
+        let pump = NotificationPump::default();
+        let parent = CancellationToken::new();
+        let (delivered, mut received) = unbounded_channel();
+
+        let connections: Vec<_> = (0..5)
+            .map(|_| connect(&pump, &parent, &delivered))
+            .collect();
+        for (index, connection) in connections.iter().enumerate() {
+            // Stale pumps are gone; their streams may be closed already.
+            let _ = connection.send(notification(index as u8));
+        }
+        assert_delivered_only(&mut received, &[4]).await;
+    }
+
+    #[tokio::test]
+    async fn stop_ends_delivery() {
+        let pump = NotificationPump::default();
+        let parent = CancellationToken::new();
+        let (delivered, mut received) = unbounded_channel();
+
+        let connection = connect(&pump, &parent, &delivered);
+        connection.send(notification(1)).unwrap();
+        assert_delivered_only(&mut received, &[1]).await;
+
+        pump.stop();
+        let _ = connection.send(notification(2));
+        assert_delivered_only(&mut received, &[]).await;
+    }
+
+    #[tokio::test]
+    async fn parent_cancellation_ends_delivery() {
+        let pump = NotificationPump::default();
+        let parent = CancellationToken::new();
+        let (delivered, mut received) = unbounded_channel();
+
+        let connection = connect(&pump, &parent, &delivered);
+        parent.cancel();
+        let _ = connection.send(notification(1));
+        assert_delivered_only(&mut received, &[]).await;
+    }
+
+    /// Fills a queue of two behind a stalled consumer, then releases the consumer.
+    async fn overflow(policy: OverflowPolicy) -> (Vec<u8>, u64) {
+        let pump = NotificationPump::default();
+        pump.configure(NotificationQueueConfig {
+            capacity: 2,
+            overflow: policy,
+        });
+        let parent = CancellationToken::new();
+        let (started, mut delivering) = unbounded_channel();
+        let (delivered, mut received) = unbounded_channel();
+        let release = Arc::new(Semaphore::new(0));
+
+        let (sender, receiver) = unbounded_channel();
+        let gate = release.clone();
+        pump.start(
+            &parent,
+            UnboundedReceiverStream::new(receiver),
+            move |delivery| {
+                let (started, delivered, gate) = (started.clone(), delivered.clone(), gate.clone());
+                async move {
+                    let _ = started.send(());
+                    gate.acquire().await.unwrap().forget();
+                    let _ = delivered.send(single(delivery).value[0]);
+                }
+            },
+        )
+        .unwrap();
+
+        sender.send(notification(1)).unwrap();
+        delivering.recv().await.unwrap();
+        for value in 2..=5 {
+            sender.send(notification(value)).unwrap();
+        }
+        timeout(Duration::from_secs(1), async {
+            while pump.dropped() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        release.add_permits(3);
+        let mut values = Vec::new();
+        for _ in 0..3 {
+            values.push(
+                timeout(Duration::from_secs(1), received.recv())
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        (values, pump.dropped())
+    }
+
+    #[tokio::test]
+    async fn drop_newest_keeps_queued_notifications() {
+        assert_eq!(
+            overflow(OverflowPolicy::DropNewest).await,
+            (vec![1, 2, 3], 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_latest_notifications() {
+        assert_eq!(
+            overflow(OverflowPolicy::DropOldest).await,
+            (vec![1, 4, 5], 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn block_drops_nothing() {
+        let pump = NotificationPump::default();
+        pump.configure(NotificationQueueConfig {
+            capacity: 1,
+            overflow: OverflowPolicy::Block,
+        });
+        let parent = CancellationToken::new();
+        let (delivered, mut received) = unbounded_channel();
+        let (sender, receiver) = unbounded_channel();
+        pump.start(
+            &parent,
+            UnboundedReceiverStream::new(receiver),
+            move |delivery| {
+                let delivered = delivered.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    let _ = delivered.send(single(delivery).value);
+                }
+            },
+        )
+        .unwrap();
+
+        for value in 0..10 {
+            sender.send(notification(value)).unwrap();
+        }
+        assert_delivered_only(&mut received, &(0..10).collect::<Vec<_>>()).await;
+        assert_eq!(pump.dropped(), 0);
+    }
+
+    /// Starts a batching pump, returning the sender feeding it and the batches it delivers.
+    fn connect_batching(
+        pump: &NotificationPump,
+        config: NotificationBatchConfig,
+    ) -> (UnboundedSender<Notification>, UnboundedReceiver<Vec<u8>>) {
+        pump.configure_batching(Some(config));
+        let (delivered, received) = unbounded_channel();
+        let (sender, receiver) = unbounded_channel();
+        pump.start(
+            &CancellationToken::new(),
+            UnboundedReceiverStream::new(receiver),
+            move |delivery| {
+                let Delivery::Batch(_, batch) = delivery else {
+                    panic!("Unexpected single notification");
+                };
+                let values = batch
+                    .into_iter()
+                    .map(|received| received.notification.value[0]);
+                let _ = delivered.send(values.collect());
+                async {}
+            },
+        )
+        .unwrap();
+        (sender, received)
+    }
+
+    #[tokio::test]
+    async fn batches_are_delivered_in_order_once_full() {
+        let pump = NotificationPump::default();
+        let (sender, mut batches) = connect_batching(
+            &pump,
+            NotificationBatchConfig {
+                interval: Duration::from_secs(3600),
+                max_items: 2,
+            },
+        );
+        for value in 1..=5 {
+            sender.send(notification(value)).unwrap();
+        }
+        for expected in [vec![1, 2], vec![3, 4]] {
+            let batch = timeout(Duration::from_secs(1), batches.recv()).await;
+            assert_eq!(batch.ok().flatten(), Some(expected));
+        }
+        // The last value waits for the interval, or for another value.
+        assert!(
+            timeout(Duration::from_millis(100), batches.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn batches_are_delivered_once_interval_elapsed() {
+        let pump = NotificationPump::default();
+        let (sender, mut batches) = connect_batching(
+            &pump,
+            NotificationBatchConfig {
+                interval: Duration::from_millis(50),
+                max_items: 100,
+            },
+        );
+        for value in 1..=3 {
+            sender.send(notification(value)).unwrap();
+        }
+        let batch = timeout(Duration::from_secs(1), batches.recv()).await;
+        assert_eq!(batch.ok().flatten(), Some(vec![1, 2, 3]));
+    }
+}
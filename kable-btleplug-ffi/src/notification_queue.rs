@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Notify;
+
+/// What to do with a notification arriving while the queue is full.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum OverflowPolicy {
+    /// Stop reading from the platform until there is room (the platform then buffers).
+    Block,
+    DropOldest,
+    DropNewest,
+}
+
+/// Bounds the notifications waiting to be delivered, per connection.
+#[derive(Copy, Clone, Debug, uniffi::Record)]
+pub struct NotificationQueueConfig {
+    pub capacity: u32,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for NotificationQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+}
+
+/// Single producer, single consumer queue applying a [`NotificationQueueConfig`].
+pub(crate) struct NotificationQueue<T> {
+    items: Mutex<VecDeque<T>>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+    readable: Notify,
+    writable: Notify,
+}
+
+impl<T> NotificationQueue<T> {
+    pub(crate) fn new(config: NotificationQueueConfig) -> Self {
+        let capacity = (config.capacity as usize).max(1);
+        Self {
+            items: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            overflow: config.overflow,
+            readable: Notify::new(),
+            writable: Notify::new(),
+        }
+    }
+
+    /// Enqueues `item`, counting any notification dropped to make room (or `item` itself).
+    pub(crate) async fn push(&self, item: T, dropped: &AtomicU64) {
+        let mut item = Some(item);
+        loop {
+            {
+                let mut items = self.items.lock().unwrap();
+                if items.len() >= self.capacity {
+                    match self.overflow {
+                        OverflowPolicy::Block => {}
+                        OverflowPolicy::DropOldest => {
+                            items.pop_front();
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        OverflowPolicy::DropNewest => {
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                }
+                if items.len() < self.capacity {
+                    items.extend(item.take());
+                    self.readable.notify_one();
+                    return;
+                }
+            }
+            self.writable.notified().await;
+        }
+    }
+
+    pub(crate) async fn pop(&self) -> T {
+        loop {
+            if let Some(item) = self.items.lock().unwrap().pop_front() {
+                self.writable.notify_one();
+                return item;
+            }
+            self.readable.notified().await;
+        }
+    }
+}
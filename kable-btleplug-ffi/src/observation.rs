@@ -0,0 +1,242 @@
+use crate::characteristic::Characteristic;
+use crate::notification_batch::TimestampedValue;
+use crate::peripheral::Inner;
+use crate::runtime;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+#[uniffi::export(callback_interface)]
+#[async_trait::async_trait]
+pub trait NotificationCallback: Send + Sync {
+    async fn notification(&self, value: TimestampedValue);
+    /// Receives coalesced values, in order, when batching is enabled.
+    async fn notification_batch(&self, values: Vec<TimestampedValue>);
+}
+
+/// Keeps an observation (see `Peripheral::observe`) alive; cancelling or dropping it stops it.
+#[derive(uniffi::Object)]
+pub struct SubscriptionHandle {
+    peripheral: Weak<Inner>,
+    key: u64,
+    released: AtomicBool,
+}
+
+impl SubscriptionHandle {
+    pub(crate) fn new(peripheral: Weak<Inner>, key: u64) -> Self {
+        Self {
+            peripheral,
+            key,
+            released: AtomicBool::new(false),
+        }
+    }
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl SubscriptionHandle {
+    /// Stops delivering values to this observer, disabling notifications if it was the last one.
+    pub async fn cancel(&self) {
+        if self.released.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        if let Some(peripheral) = self.peripheral.upgrade() {
+            peripheral.release_observer(self.key).await;
+        }
+    }
+}
+
+impl Drop for SubscriptionHandle {
+    fn drop(&mut self) {
+        if self.released.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let Ok(runtime) = runtime::handle() else {
+            return;
+        };
+        let peripheral = self.peripheral.clone();
+        let key = self.key;
+        runtime.spawn(async move {
+            if let Some(peripheral) = peripheral.upgrade() {
+                peripheral.release_observer(key).await;
+            }
+        });
+    }
+}
+
+struct Observed {
+    characteristic: Characteristic,
+    callbacks: HashMap<u64, Arc<Box<dyn NotificationCallback>>>,
+}
+
+/// Observers of a peripheral's characteristics, keyed by characteristic instance. Observers of the
+/// same characteristic share its notification subscription.
+#[derive(Default)]
+pub(crate) struct Observers {
+    observed: Mutex<HashMap<u32, Observed>>,
+    next_key: AtomicU64,
+}
+
+impl Observers {
+    /// Returns the observer's key, and whether it is the characteristic's first observer.
+    pub(crate) fn add(
+        &self,
+        characteristic: Characteristic,
+        callback: Box<dyn NotificationCallback>,
+    ) -> (u64, bool) {
+        let key = self.next_key.fetch_add(1, Ordering::Relaxed);
+        let mut observed = self.observed.lock().unwrap();
+        let entry = observed
+            .entry(characteristic.instance)
+            .or_insert_with(|| Observed {
+                characteristic,
+                callbacks: HashMap::new(),
+            });
+        entry.callbacks.insert(key, Arc::new(callback));
+        (key, entry.callbacks.len() == 1)
+    }
+
+    /// Returns the characteristic if it no longer has observers. Observers are looked up by key
+    /// alone, as their characteristic's instance may change across reconnects (see [`Self::rekey`]).
+    pub(crate) fn remove(&self, key: u64) -> Option<Characteristic> {
+        let mut observed = self.observed.lock().unwrap();
+        let (&instance, entry) = observed
+            .iter_mut()
+            .find(|(_, entry)| entry.callbacks.contains_key(&key))?;
+        entry.callbacks.remove(&key);
+        if !entry.callbacks.is_empty() {
+            return None;
+        }
+        observed
+            .remove(&instance)
+            .map(|observed| observed.characteristic)
+    }
+
+    /// Moves observers to their characteristic as rediscovered by `rediscovered` (which receives the
+    /// instance observed so far), dropping those whose characteristic no longer exists.
+    pub(crate) fn rekey(&self, rediscovered: impl Fn(u32) -> Option<Characteristic>) {
+        let mut observed = self.observed.lock().unwrap();
+        *observed = observed
+            .drain()
+            .filter_map(|(instance, entry)| {
+                let characteristic = rediscovered(instance)?;
+                let entry = Observed {
+                    characteristic,
+                    callbacks: entry.callbacks,
+                };
+                Some((entry.characteristic.instance, entry))
+            })
+            .collect();
+    }
+
+    /// Drops the characteristic's observers, e.g. as it no longer exists.
+    pub(crate) fn forget(&self, instance: u32) {
+        self.observed.lock().unwrap().remove(&instance);
+    }
+
+    pub(crate) fn is_observed(&self, instance: u32) -> bool {
+        self.observed.lock().unwrap().contains_key(&instance)
+    }
+
+    pub(crate) fn characteristics(&self) -> Vec<Characteristic> {
+        self.observed
+            .lock()
+            .unwrap()
+            .values()
+            .map(|observed| observed.characteristic.clone())
+            .collect()
+    }
+
+    pub(crate) fn callbacks(&self, instance: u32) -> Vec<Arc<Box<dyn NotificationCallback>>> {
+        self.observed
+            .lock()
+            .unwrap()
+            .get(&instance)
+            .map(|observed| observed.callbacks.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uuid::Uuid;
+    use btleplug::api::CharPropFlags;
+
+    struct Ignore;
+
+    #[async_trait::async_trait]
+    impl NotificationCallback for Ignore {
+        async fn notification(&self, _: TimestampedValue) {}
+        async fn notification_batch(&self, _: Vec<TimestampedValue>) {}
+    }
+
+    fn characteristic(instance: u32) -> Characteristic {
+        Characteristic {
+            uuid: Uuid::from_u16(0x2A37),
+            service: Uuid::from_u16(0x180D),
+            instance,
+            properties: CharPropFlags::NOTIFY.into(),
+            descriptors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn observers_share_a_characteristic_until_the_last_leaves() {
+        let observers = Observers::default();
+        let (first, is_first) = observers.add(characteristic(1), Box::new(Ignore));
+        assert!(is_first);
+        let (second, is_first) = observers.add(characteristic(1), Box::new(Ignore));
+        assert!(!is_first);
+        assert_eq!(observers.callbacks(1).len(), 2);
+
+        assert!(observers.remove(first).is_none());
+        assert!(observers.is_observed(1));
+        let released = observers.remove(second).map(|c| c.instance);
+        assert_eq!(released, Some(1));
+        assert!(!observers.is_observed(1));
+    }
+
+    #[test]
+    fn characteristics_are_counted_separately() {
+        let observers = Observers::default();
+        let (first, _) = observers.add(characteristic(1), Box::new(Ignore));
+        let (_, is_first) = observers.add(characteristic(2), Box::new(Ignore));
+        assert!(is_first);
+
+        assert_eq!(observers.remove(first).map(|c| c.instance), Some(1));
+        assert!(observers.is_observed(2));
+    }
+
+    #[test]
+    fn removing_twice_releases_once() {
+        let observers = Observers::default();
+        let (key, _) = observers.add(characteristic(1), Box::new(Ignore));
+        assert!(observers.remove(key).is_some());
+        assert!(observers.remove(key).is_none());
+    }
+
+    #[test]
+    fn rekey_follows_rediscovered_characteristics() {
+        let observers = Observers::default();
+        let (moved, _) = observers.add(characteristic(1), Box::new(Ignore));
+        let (shared, _) = observers.add(characteristic(2), Box::new(Ignore));
+        let (last, _) = observers.add(characteristic(2), Box::new(Ignore));
+        let (gone, _) = observers.add(characteristic(3), Box::new(Ignore));
+
+        // 1 and 2 swap places; 3 no longer exists.
+        observers.rekey(|instance| match instance {
+            1 => Some(characteristic(2)),
+            2 => Some(characteristic(1)),
+            _ => None,
+        });
+        assert_eq!(observers.callbacks(1).len(), 2);
+        assert_eq!(observers.callbacks(2).len(), 1);
+        assert!(!observers.is_observed(3));
+        assert!(observers.remove(gone).is_none());
+
+        // Keys still release their observer after moving.
+        assert_eq!(observers.remove(moved).map(|c| c.instance), Some(2));
+        assert!(observers.remove(shared).is_none());
+        assert_eq!(observers.remove(last).map(|c| c.instance), Some(1));
+    }
+}
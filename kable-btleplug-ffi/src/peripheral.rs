@@ -1,42 +1,110 @@
 use crate::Result;
+use crate::access_list;
+#[cfg(target_os = "linux")]
+use crate::address_type::AddressType;
 use crate::cancellation_handle::CancellationHandle;
 use crate::characteristic::Characteristic;
+use crate::connection_state::ConnectionState;
 use crate::descriptor::Descriptor;
+use crate::disconnect_reason::DisconnectReason;
+use crate::discovery::DiscoverySession;
+use crate::events;
+use crate::gatt_queue::{GattQueue, OperationOptions};
+use crate::notification_batch::{NotificationBatchConfig, TimestampedValue};
+#[cfg(not(target_os = "linux"))]
+use crate::notification_pump::Notification;
+use crate::notification_pump::{Delivery, NotificationPump, Source};
+use crate::notification_queue::NotificationQueueConfig;
+use crate::observation::{NotificationCallback, Observers, SubscriptionHandle};
 use crate::peripheral_id::PeripheralId;
 use crate::peripheral_properties::PeripheralProperties;
+use crate::reconnect::{ReconnectEvent, ReconnectPolicy};
+use crate::registry;
 use crate::service::Service;
+use crate::subscription_mode::SubscriptionMode;
 use crate::uuid::Uuid;
 use crate::write_type::WriteType;
 use crate::{Error, get_adapter};
-use btleplug::api::{Central, CentralEvent, Peripheral as _, ScanFilter};
-use std::sync::{Arc, Mutex};
+use btleplug::api::{Central, CentralEvent, CentralState, CharPropFlags, Peripheral as _};
+use btleplug::platform::Adapter;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::time::timeout;
+#[cfg(not(target_os = "linux"))]
 use tokio_stream::StreamExt;
 use tokio_util::sync::CancellationToken;
 
+/// How long btleplug may take to learn about a device BlueZ has just connected to.
+#[cfg(target_os = "linux")]
+const DEVICE_APPEARANCE_TIMEOUT: Duration = Duration::from_secs(2);
+
 #[uniffi::export(callback_interface)]
 #[async_trait::async_trait]
 pub trait PeripheralCallbacks: Send + Sync {
+    fn state_changed(&self, state: ConnectionState);
     fn connected(&self);
-    fn disconnected(&self);
-    async fn notification(&self, uuid: Uuid, data: Vec<u8>);
+    fn disconnected(&self, reason: DisconnectReason);
+    fn reconnect(&self, event: ReconnectEvent);
+    /// A subscribed characteristic no longer exists after a reconnect, or its notifications could not
+    /// be re-enabled; it is no longer tracked.
+    fn subscription_lost(&self, characteristic: Characteristic);
+    async fn notification(&self, characteristic: Characteristic, value: TimestampedValue);
+    /// Receives coalesced notifications, in order, when batching is enabled (see
+    /// [`Peripheral::set_notification_batching`]).
+    async fn notification_batch(
+        &self,
+        characteristic: Characteristic,
+        values: Vec<TimestampedValue>,
+    );
 }
 
-#[derive(Clone, uniffi::Object)]
+#[derive(uniffi::Object)]
 pub struct Peripheral {
+    inner: Arc<Inner>,
+}
+
+/// State shared with the peripheral's background tasks (event loop, automatic reconnect). They hold
+/// it rather than the [`Peripheral`] itself, so that dropping the [`Peripheral`] still cancels them.
+pub(crate) struct Inner {
     id: Arc<PeripheralId>,
     callbacks: Arc<Box<dyn PeripheralCallbacks>>,
     cancellation: CancellationHandle,
     cached_peripheral: Arc<Mutex<Option<btleplug::platform::Peripheral>>>,
+
+    /// Characteristics subscribed to through [`Peripheral::subscribe`], keyed by instance, so that
+    /// they can be restored after an automatic reconnect.
+    subscriptions: Arc<Mutex<HashMap<u32, Characteristic>>>,
+    reconnect_policy: Arc<Mutex<Option<ReconnectPolicy>>>,
+    /// Cancels the automatic reconnect in progress, if any.
+    reconnect_cancellation: Arc<Mutex<Option<CancellationToken>>>,
+    disconnect_requested: Arc<AtomicBool>,
+    state: Arc<Mutex<ConnectionState>>,
+    /// Serializes GATT operations.
+    gatt: Arc<GattQueue>,
+    /// Observers registered through [`Peripheral::observe`].
+    observers: Arc<Observers>,
+    /// Instances of the characteristics with notifications enabled on the current connection.
+    notifying: Arc<Mutex<HashSet<u32>>>,
+    /// Serializes enabling and disabling notifications, which observers share.
+    subscription_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Forwards the notifications of the current connection.
+    notifications: Arc<NotificationPump>,
+    /// GATT table read by the latest service discovery.
+    #[cfg(target_os = "linux")]
+    gatt_table: Arc<Mutex<crate::bluez::GattTable>>,
+    /// Keeps this peripheral's events routed to it (see [`events::register`]).
+    _events: Arc<events::Registration>,
 }
 
-impl Peripheral {
+impl Inner {
     async fn get_platform(&self) -> Result<btleplug::platform::Peripheral> {
         let platform = self.cached_peripheral.lock().unwrap().clone();
         if platform.is_none() {
             match get_adapter()
-                .await
+                .await?
                 .peripheral(&self.id.platform)
                 .await
                 .map_err(Into::into)
@@ -55,172 +123,657 @@ impl Peripheral {
         platform: btleplug::platform::Peripheral,
         cancellation_handle: Arc<CancellationHandle>,
     ) -> Result<()> {
-        if platform.is_connected().await? {
-            return Ok(());
-        }
-
+        access_list::check(&self.id, platform.address())?;
         let token = cancellation_handle.token();
-        tokio::select! {
-            _ = token.cancelled() => return Err(Error::Cancelled),
-            _ = platform.connect() => {}
+        if !platform.is_connected().await? {
+            self.set_state(ConnectionState::Connecting);
+            tokio::select! {
+                _ = token.cancelled() => return Err(Error::Cancelled),
+                result = platform.connect() => result.map_err(Error::from_connect)?,
+            }
         }
 
-        let callbacks = self.callbacks.clone();
-        let mut notifications = match platform.notifications().await {
+        // BlueZ reports which characteristic a value is of, btleplug only its UUIDs.
+        #[cfg(target_os = "linux")]
+        let notifications = match self.id.object_path() {
+            Some(path) => crate::bluez::notifications(&path).await,
+            None => Err(Error::DeviceNotFound),
+        };
+        #[cfg(not(target_os = "linux"))]
+        let notifications = platform
+            .notifications()
+            .await
+            .map(|notifications| notifications.map(Notification::from))
+            .map_err(Error::from);
+        let notifications = match notifications {
             Ok(notifications) => notifications,
             Err(e) => {
                 let _ = timeout(Duration::from_secs(1), platform.disconnect()).await;
-                return Err(e.into());
+                return Err(Error::NotificationSetupFailed(e.to_string()));
             }
         };
+        let callbacks = self.callbacks.clone();
+        let subscriptions = self.subscriptions.clone();
+        let observers = self.observers.clone();
+        #[cfg(target_os = "linux")]
+        let gatt_table = self.gatt_table.clone();
+        self.notifications
+            .start(&self.cancellation.token(), notifications, move |delivery| {
+                let source = match &delivery {
+                    Delivery::Single(received) => received.notification.source,
+                    Delivery::Batch(source, _) => *source,
+                };
+                #[cfg(target_os = "linux")]
+                let services = || gatt_table.lock().unwrap().services.clone();
+                #[cfg(not(target_os = "linux"))]
+                let services = || Service::from_platform(platform.services());
+                let sources = notification_sources(&subscriptions, &observers, services, source);
+                deliver(callbacks.clone(), observers.clone(), sources, delivery)
+            })?;
+
+        Ok(())
+    }
 
-        std::thread::spawn(move || {
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .unwrap();
+    async fn establish_connection(
+        &self,
+        cancellation_handle: Arc<CancellationHandle>,
+    ) -> Result<()> {
+        access_list::check_id(&self.id)?;
+        if let Ok(platform) = self.get_platform().await {
+            return self.platform_connect(platform, cancellation_handle).await;
+        }
 
-            rt.block_on(async move {
-                loop {
-                    tokio::select! {
-                        _ = token.cancelled() => break,
-                        Some(notification) = notifications.next() =>
-                            callbacks.notification(notification.uuid.into(), notification.value).await
-                    }
-                }
-            });
-        });
+        // IDs built from an address can be connected to directly, skipping discovery entirely.
+        #[cfg(target_os = "linux")]
+        if let Some(address_type) = self.id.address_type {
+            match self
+                .directed_connect(address_type, &cancellation_handle)
+                .await
+            {
+                Ok(platform) => return self.platform_connect(platform, cancellation_handle).await,
+                // `ConnectDevice` is experimental (`bluetoothd -E`); scan for the device instead.
+                Err(Error::NotSupported(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
 
-        Ok(())
+        // Ooph. We're in a bad place because btleplug removes peripherals from the adapter when
+        // they disconnect. This means that we have to re-scan until the device shows back up in
+        // the adapter and then attempt connection.
+        self.set_state(ConnectionState::ScanningForDevice);
+        let adapter = get_adapter().await?;
+        let (_registration, mut events) = events::register(&self.id.platform).await?;
+        let discovery = DiscoverySession::targeted(registry::advertised_services(&self.id)).await?;
+        let found = self
+            .await_discovery(&adapter, &mut events, &cancellation_handle)
+            .await;
+        // Stop before connecting: scanning while connecting degrades the connection.
+        discovery.stop().await;
+        found?;
+
+        let platform = self.get_platform().await?;
+        self.platform_connect(platform, cancellation_handle).await
     }
-}
 
-#[uniffi::export(async_runtime = "tokio")]
-impl Peripheral {
-    #[uniffi::constructor]
-    pub fn new(id: Arc<PeripheralId>, callbacks: Box<dyn PeripheralCallbacks>) -> Self {
-        let callbacks = Arc::new(callbacks);
-        let token = CancellationToken::new();
+    /// Aborts a connection attempt that may still be pending in the platform (e.g. timed out).
+    async fn abort_connection(&self) {
+        if let Ok(platform) = self.get_platform().await {
+            let _ = timeout(Duration::from_secs(1), platform.disconnect()).await;
+        }
+    }
 
-        let peripheral = Self {
-            id: id.clone(),
-            callbacks: callbacks.clone(),
-            cancellation: CancellationHandle::from_token(token.clone()),
-            cached_peripheral: Arc::new(Mutex::new(None)),
-        };
-        let cached_peripheral = peripheral.cached_peripheral.clone();
+    /// Settles the connect phase (`ScanningForDevice` or `Connecting`) an abandoned connection
+    /// attempt was left in, leaving any other state (e.g. entered by a new attempt) alone.
+    fn settle_connect_phase(&self, reason: DisconnectReason) {
+        for phase in [
+            ConnectionState::ScanningForDevice,
+            ConnectionState::Connecting,
+        ] {
+            let disconnected = ConnectionState::Disconnected {
+                reason: Some(reason.clone()),
+            };
+            update_state(&self.state, &**self.callbacks, Some(&phase), disconnected);
+        }
+    }
 
-        std::thread::spawn(|| {
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .unwrap();
+    /// Updates the state, notifying callbacks only on an actual change.
+    fn set_state(&self, state: ConnectionState) {
+        update_state(&self.state, &**self.callbacks, None, state);
+    }
 
-            rt.block_on(async move {
-                let adapter = get_adapter().await;
-                let mut events = adapter.events().await.unwrap();
-                loop {
-                    tokio::select! {
-                        _ = token.cancelled() => {
-                            break;
-                        },
-                        Some(event) = events.next() => match event {
-                            CentralEvent::DeviceConnected(connected)
-                                if connected == id.platform => {
-                                    callbacks.connected()
-                                }
-                            CentralEvent::DeviceDisconnected(disconnected)
-                                if disconnected == id.platform => {
-                                    *cached_peripheral.lock().unwrap() = None;
-                                    callbacks.disconnected()
-                                }
-                            _ => {}
-                        },
-                    }
-                }
-            });
+    /// Enters a transient state, settled by the returned guard unless its operation moves on.
+    fn enter_state(
+        &self,
+        transient: ConnectionState,
+        platform: &btleplug::platform::Peripheral,
+        reason: DisconnectReason,
+    ) -> SettleState {
+        self.set_state(transient.clone());
+        SettleState {
+            transient: Some(transient),
+            state: self.state.clone(),
+            callbacks: self.callbacks.clone(),
+            platform: platform.clone(),
+            reason,
+        }
+    }
+
+    async fn on_disconnected(&self, peripheral: Weak<Self>) {
+        self.notifications.stop();
+        self.notifying.lock().unwrap().clear();
+        *self.cached_peripheral.lock().unwrap() = None;
+        let requested = self.disconnect_requested.swap(false, Ordering::Relaxed);
+        let reason = self.disconnect_reason(requested).await;
+        self.set_state(ConnectionState::Disconnected {
+            reason: Some(reason.clone()),
         });
+        self.callbacks.disconnected(reason);
 
-        peripheral
+        if requested {
+            return;
+        }
+        let Some(policy) = self.reconnect_policy.lock().unwrap().clone() else {
+            return;
+        };
+        let token = self.cancellation.token().child_token();
+        if let Some(previous) = self
+            .reconnect_cancellation
+            .lock()
+            .unwrap()
+            .replace(token.clone())
+        {
+            previous.cancel();
+        }
+        // Cannot fail: this runs on the (already built) shared runtime.
+        let _ = crate::runtime::spawn(token.clone(), reconnect(peripheral, policy, token));
     }
 
-    async fn properties(&self) -> Result<PeripheralProperties> {
-        let platform = self.get_platform().await?.properties().await?.unwrap();
-        Ok(PeripheralProperties::new(self.id.clone(), platform))
+    /// Our own bookkeeping takes precedence over what the platform reports.
+    async fn disconnect_reason(&self, requested: bool) -> DisconnectReason {
+        if requested {
+            return DisconnectReason::LocalRequest;
+        }
+
+        #[cfg(target_os = "linux")]
+        let reported = match self.id.object_path() {
+            Some(path) => crate::bluez::take_disconnect_reason(&path).await,
+            None => None,
+        };
+        #[cfg(not(target_os = "linux"))]
+        let reported = None;
+
+        let adapter_state = match get_adapter().await {
+            Ok(adapter) => adapter.adapter_state().await,
+            Err(_) => return DisconnectReason::AdapterPoweredOff,
+        };
+        if !matches!(adapter_state, Ok(CentralState::PoweredOn)) {
+            return DisconnectReason::AdapterPoweredOff;
+        }
+        reported.unwrap_or(DisconnectReason::Unknown { code: None })
     }
 
-    async fn connect(&self, cancellation_handle: Arc<CancellationHandle>) -> bool {
-        if let Ok(platform) = self.get_platform().await {
-            return self
-                .platform_connect(platform, cancellation_handle)
-                .await
-                .is_ok();
+    fn cancel_reconnect(&self) {
+        if let Some(token) = self.reconnect_cancellation.lock().unwrap().take() {
+            token.cancel();
         }
+    }
 
-        // Ooph. We're in a bad place because btleplug removes peripherals from the adapter when
-        // they disconnect. This means that we have to re-scan until the device shows back up in
-        // the adapter and then attempt connection.
-        let adapter = get_adapter().await;
-        let mut events = match adapter.events().await {
-            Ok(events) => events,
-            Err(_) => return false,
-        };
-        if adapter.start_scan(ScanFilter::default()).await.is_err() {
-            return false;
+    /// Disables notifications once a characteristic has neither observers nor a subscription.
+    pub(crate) async fn release_observer(&self, key: u64) {
+        // May fail if the connection was lost, in which case notifications are already off.
+        let _ = self
+            .gatt
+            .run(None, None, async {
+                let _lock = self.subscription_lock.lock().await;
+                let Some(characteristic) = self.observers.remove(key) else {
+                    return Ok(());
+                };
+                // Still needed by `subscribe`.
+                if self
+                    .subscriptions
+                    .lock()
+                    .unwrap()
+                    .contains_key(&characteristic.instance)
+                {
+                    return Ok(());
+                }
+                self.disable_notifications(characteristic).await
+            })
+            .await;
+    }
+
+    /// Re-enables the subscriptions made through [`Peripheral::subscribe`] and
+    /// [`Peripheral::observe`] once services are rediscovered after a reconnect, following their
+    /// characteristic should its instance have changed (see [`rediscover`]). Subscriptions whose
+    /// characteristic no longer exists, or whose notifications cannot be re-enabled, are reported
+    /// through [`PeripheralCallbacks::subscription_lost`] and forgotten. Runs as part of
+    /// [`Peripheral::discover_services`]'s GATT operation.
+    async fn restore_subscriptions(&self) {
+        let _lock = self.subscription_lock.lock().await;
+        let table: Vec<_> = self
+            .services()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|service| service.characteristics)
+            .collect();
+        let mut previous: HashMap<_, _> = self.subscriptions.lock().unwrap().clone();
+        for characteristic in self.observers.characteristics() {
+            previous.insert(characteristic.instance, characteristic);
         }
+        let rediscovered: HashMap<_, _> = previous
+            .iter()
+            .map(|(&instance, characteristic)| (instance, rediscover(&table, characteristic)))
+            .collect();
+        let rediscovered_as = |instance| rediscovered.get(&instance).cloned().flatten();
+        {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            *subscriptions = subscriptions
+                .keys()
+                .filter_map(|&instance| rediscovered_as(instance))
+                .map(|characteristic| (characteristic.instance, characteristic))
+                .collect();
+        }
+        self.observers.rekey(rediscovered_as);
+
+        for (instance, characteristic) in previous {
+            let Some(current) = rediscovered_as(instance) else {
+                self.callbacks.subscription_lost(characteristic);
+                continue;
+            };
+            if self.enable_notifications(current.clone()).await.is_err() {
+                self.subscriptions.lock().unwrap().remove(&current.instance);
+                self.observers.forget(current.instance);
+                self.callbacks.subscription_lost(current);
+            }
+        }
+    }
+
+    /// Enables notifications unless already enabled on this connection (e.g. restored by
+    /// [`Inner::restore_subscriptions`] before the caller replayed its own subscriptions).
+    async fn enable_notifications(&self, characteristic: Characteristic) -> Result<()> {
+        if self
+            .notifying
+            .lock()
+            .unwrap()
+            .contains(&characteristic.instance)
+        {
+            return Ok(());
+        }
+        let instance = characteristic.instance;
+        #[cfg(target_os = "linux")]
+        crate::bluez::start_notify(&self.characteristic_id(instance)?).await?;
+        #[cfg(not(target_os = "linux"))]
+        self.get_platform()
+            .await?
+            .subscribe(&characteristic.into())
+            .await?;
+        self.notifying.lock().unwrap().insert(instance);
+        Ok(())
+    }
+
+    /// Must run within a GATT operation (see [`GattQueue::run`]), as must the other operations on
+    /// characteristics.
+    async fn disable_notifications(&self, characteristic: Characteristic) -> Result<()> {
+        self.notifying
+            .lock()
+            .unwrap()
+            .remove(&characteristic.instance);
+        #[cfg(target_os = "linux")]
+        return crate::bluez::stop_notify(&self.characteristic_id(characteristic.instance)?).await;
+        #[cfg(not(target_os = "linux"))]
+        self.get_platform()
+            .await?
+            .unsubscribe(&characteristic.into())
+            .await
+            .map_err(Into::into)
+    }
 
+    async fn read_characteristic(&self, characteristic: Characteristic) -> Result<Vec<u8>> {
+        #[cfg(target_os = "linux")]
+        return crate::bluez::read(&self.characteristic_id(characteristic.instance)?).await;
+        #[cfg(not(target_os = "linux"))]
+        self.get_platform()
+            .await?
+            .read(&characteristic.into())
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn write_characteristic(
+        &self,
+        characteristic: Characteristic,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        return crate::bluez::write(
+            &self.characteristic_id(characteristic.instance)?,
+            data,
+            write_type,
+        )
+        .await;
+        #[cfg(not(target_os = "linux"))]
+        self.get_platform()
+            .await?
+            .write(&characteristic.into(), data, write_type.into())
+            .await
+            .map_err(Into::into)
+    }
+
+    /// The GATT table read by the latest service discovery.
+    async fn services(&self) -> Result<Vec<Service>> {
+        #[cfg(target_os = "linux")]
+        return Ok(self.gatt_table.lock().unwrap().services.clone());
+        #[cfg(not(target_os = "linux"))]
+        Ok(Service::from_platform(
+            self.get_platform().await?.services(),
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn characteristic_id(&self, instance: u32) -> Result<bluez_async::CharacteristicId> {
+        self.gatt_table.lock().unwrap().characteristic(instance)
+    }
+
+    /// Waits until the adapter has (re)discovered this peripheral.
+    async fn await_discovery(
+        &self,
+        adapter: &Adapter,
+        events: &mut UnboundedReceiver<CentralEvent>,
+        cancellation_handle: &CancellationHandle,
+    ) -> Result<()> {
         let peripheral_token = self.cancellation.token();
         let connect_token = cancellation_handle.token();
         loop {
             tokio::select! {
-                _ = peripheral_token.cancelled() => break,
-                _ = connect_token.cancelled() => break,
-                Some(event) = events.next() => match event {
-                    CentralEvent::DeviceConnected(_) | CentralEvent::DeviceUpdated(_)
+                _ = peripheral_token.cancelled() => return Err(Error::Cancelled),
+                _ = connect_token.cancelled() => return Err(Error::Cancelled),
+                Some(event) = events.recv() => match event {
+                    CentralEvent::DeviceDiscovered(_)
+                    | CentralEvent::DeviceUpdated(_)
+                    | CentralEvent::DeviceConnected(_)
                         if adapter.peripheral(&self.id.platform).await.is_ok() => {
-                            break;
+                            return Ok(());
                         },
                     _ => {}
                 }
             }
         }
+    }
 
-        if let Ok(platform) = self.get_platform().await {
-            self.platform_connect(platform, cancellation_handle)
-                .await
-                .is_ok()
-        } else {
-            false
+    async fn discover_services(
+        &self,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<()> {
+        let platform = self.get_platform().await?;
+        let settle = self.enter_state(
+            ConnectionState::DiscoveringServices,
+            &platform,
+            DisconnectReason::ConnectionFailed,
+        );
+        self.gatt
+            .run(None, cancellation_handle, async {
+                platform.discover_services().await?;
+                #[cfg(target_os = "linux")]
+                if let Some(path) = self.id.object_path() {
+                    *self.gatt_table.lock().unwrap() = crate::bluez::gatt_table(&path).await?;
+                }
+                self.restore_subscriptions().await;
+                Ok(())
+            })
+            .await?;
+        settle.complete(ConnectionState::Ready);
+        Ok(())
+    }
+
+    /// Has BlueZ connect to a device it does not know about yet, without scanning for it first.
+    #[cfg(target_os = "linux")]
+    async fn directed_connect(
+        &self,
+        address_type: AddressType,
+        cancellation_handle: &CancellationHandle,
+    ) -> Result<btleplug::platform::Peripheral> {
+        let address = self.id.bd_addr().ok_or(Error::DeviceNotFound)?;
+        access_list::check(&self.id, address)?;
+        let device = self.id.object_path().ok_or(Error::DeviceNotFound)?;
+        let adapter = device
+            .rsplit_once('/')
+            .map(|(adapter, _)| adapter.to_string())
+            .ok_or(Error::DeviceNotFound)?;
+        // Registered first: the device may only reach btleplug after `ConnectDevice` returns.
+        let (_registration, mut events) = events::register(&self.id.platform).await?;
+
+        let token = cancellation_handle.token();
+        self.set_state(ConnectionState::Connecting);
+        tokio::select! {
+            _ = token.cancelled() => return Err(Error::Cancelled),
+            result = crate::bluez::connect_device(adapter, address, address_type) => {
+                result?;
+            }
+        }
+
+        let appeared = async {
+            loop {
+                if let Ok(platform) = self.get_platform().await {
+                    return Some(platform);
+                }
+                events.recv().await?;
+            }
+        };
+        let platform = tokio::select! {
+            _ = token.cancelled() => None,
+            platform = timeout(DEVICE_APPEARANCE_TIMEOUT, appeared) => platform.ok().flatten(),
+        };
+        match platform {
+            Some(platform) => Ok(platform),
+            None => {
+                // Don't leave BlueZ connected to a device nobody holds.
+                crate::bluez::disconnect_device(device);
+                Err(if token.is_cancelled() {
+                    Error::Cancelled
+                } else {
+                    Error::DeviceNotFound
+                })
+            }
         }
     }
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl Peripheral {
+    #[uniffi::constructor]
+    pub fn new(
+        id: Arc<PeripheralId>,
+        callbacks: Box<dyn PeripheralCallbacks>,
+    ) -> Result<Arc<Self>> {
+        let token = CancellationToken::new();
+        let handle = crate::runtime::handle()?;
+        // Constructors are called from foreign threads, outside of any runtime.
+        let (registration, mut events) = handle.block_on(events::register(&id.platform))?;
+        #[cfg(target_os = "linux")]
+        crate::bluez::watch_disconnect_reasons()?;
+
+        let inner = Arc::new_cyclic(|weak: &Weak<Inner>| {
+            let peripheral = weak.clone();
+            let events_token = token.clone();
+            handle.spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = events_token.cancelled() => break,
+                        Some(event) = events.recv() => {
+                            // Not yet constructed; dropping cancels this loop instead.
+                            let Some(strong) = peripheral.upgrade() else {
+                                continue;
+                            };
+                            match event {
+                                CentralEvent::DeviceConnected(_) => strong.callbacks.connected(),
+                                CentralEvent::DeviceDisconnected(_) => {
+                                    strong.on_disconnected(peripheral.clone()).await
+                                }
+                                // The connection's notification stream may never end.
+                                CentralEvent::StateUpdate(CentralState::PoweredOff) => {
+                                    strong.notifications.stop()
+                                }
+                                _ => {}
+                            }
+                        },
+                    }
+                }
+            });
+
+            Inner {
+                id,
+                callbacks: Arc::new(callbacks),
+                cancellation: CancellationHandle::from_token(token),
+                cached_peripheral: Arc::new(Mutex::new(None)),
+                subscriptions: Arc::new(Mutex::new(HashMap::new())),
+                reconnect_policy: Arc::new(Mutex::new(None)),
+                reconnect_cancellation: Arc::new(Mutex::new(None)),
+                disconnect_requested: Arc::new(AtomicBool::new(false)),
+                state: Arc::new(Mutex::new(ConnectionState::Disconnected { reason: None })),
+                gatt: Arc::new(GattQueue::default()),
+                observers: Arc::new(Observers::default()),
+                notifying: Arc::new(Mutex::new(HashSet::new())),
+                subscription_lock: Arc::new(tokio::sync::Mutex::new(())),
+                notifications: Arc::new(NotificationPump::default()),
+                #[cfg(target_os = "linux")]
+                gatt_table: Arc::new(Mutex::new(crate::bluez::GattTable::default())),
+                _events: Arc::new(registration),
+            }
+        });
+        Ok(Arc::new(Self { inner }))
+    }
 
-    async fn disconnect(&self) -> bool {
-        match self.get_platform().await {
-            Err(_) => true,
-            Ok(platform) => platform.disconnect().await.is_ok(),
+    pub fn state(&self) -> ConnectionState {
+        self.inner.state.lock().unwrap().clone()
+    }
+
+    /// Timeout of GATT operations not given one explicitly (see [`OperationOptions::timeout`]).
+    pub fn set_operation_timeout(&self, timeout: Duration) {
+        self.inner.gatt.set_default_timeout(timeout);
+    }
+
+    /// Bounds the notifications awaiting delivery; applies from the next connection on.
+    pub fn set_notification_queue(&self, config: NotificationQueueConfig) {
+        self.inner.notifications.configure(config);
+    }
+
+    /// Enables (or, with `None`, disables) coalesced notification delivery; applies from the next
+    /// connection on.
+    pub fn set_notification_batching(&self, config: Option<NotificationBatchConfig>) {
+        self.inner.notifications.configure_batching(config);
+    }
+
+    /// Number of notifications dropped because the notification queue was full.
+    pub fn dropped_notifications(&self) -> u64 {
+        self.inner.notifications.dropped()
+    }
+
+    /// Enables (or, with `None`, disables) automatic reconnection after unexpected disconnects.
+    pub fn set_reconnect_policy(&self, policy: Option<ReconnectPolicy>) {
+        if policy.is_none() {
+            self.inner.cancel_reconnect();
         }
+        *self.inner.reconnect_policy.lock().unwrap() = policy;
     }
 
-    async fn discover_services(&self) -> Result<()> {
-        self.get_platform()
+    async fn properties(&self) -> Result<PeripheralProperties> {
+        let platform = self
+            .inner
+            .get_platform()
             .await?
-            .discover_services()
-            .await
-            .map_err(Into::into)
+            .properties()
+            .await?
+            .unwrap();
+        Ok(PeripheralProperties::new(self.inner.id.clone(), platform))
+    }
+
+    /// Connects, giving up after `timeout` (if provided). The timeout covers the whole sequence:
+    /// rescanning for the device, connecting and setting up the notification stream.
+    async fn connect(
+        &self,
+        cancellation_handle: Arc<CancellationHandle>,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        self.inner.cancel_reconnect();
+        self.inner
+            .disconnect_requested
+            .store(false, Ordering::Relaxed);
+        let connection = self.inner.establish_connection(cancellation_handle);
+        let result = match timeout {
+            None => connection.await,
+            // On expiry, dropping the connection future ends its discovery session (if any).
+            Some(duration) => match tokio::time::timeout(duration, connection).await {
+                Ok(result) => result,
+                Err(_) => {
+                    self.inner.abort_connection().await;
+                    Err(Error::TimedOut(duration))
+                }
+            },
+        };
+        if let Err(e) = &result {
+            let reason = match e {
+                Error::Cancelled => DisconnectReason::LocalRequest,
+                _ => DisconnectReason::ConnectionFailed,
+            };
+            self.inner.set_state(ConnectionState::Disconnected {
+                reason: Some(reason),
+            });
+        }
+        result
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.inner.cancel_reconnect();
+        self.inner
+            .disconnect_requested
+            .store(true, Ordering::Relaxed);
+        self.inner.notifications.stop();
+        self.inner.notifying.lock().unwrap().clear();
+        let disconnected = ConnectionState::Disconnected {
+            reason: Some(DisconnectReason::LocalRequest),
+        };
+        let Ok(platform) = self.inner.get_platform().await else {
+            // Unknown to the adapter, so it cannot be connected.
+            self.inner.set_state(disconnected);
+            return Ok(());
+        };
+        let settle = self.inner.enter_state(
+            ConnectionState::Disconnecting,
+            &platform,
+            DisconnectReason::LocalRequest,
+        );
+        platform.disconnect().await?;
+        // The disconnect event may never arrive if the link was already down.
+        settle.complete(disconnected);
+        Ok(())
+    }
+
+    async fn discover_services(
+        &self,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<()> {
+        self.inner.discover_services(cancellation_handle).await
     }
 
     async fn services(&self) -> Result<Vec<Service>> {
-        self.get_platform()
-            .await
-            .map(|p| p.services().into_iter().map(Into::into).collect())
+        self.inner.services().await
     }
 
-    async fn read(&self, characteristic: Characteristic) -> Result<Vec<u8>> {
-        self.get_platform()
-            .await?
-            .read(&characteristic.into())
+    async fn read(
+        &self,
+        characteristic: Characteristic,
+        options: Option<OperationOptions>,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<Vec<u8>> {
+        self.inner
+            .gatt
+            .run(options, cancellation_handle, async {
+                let characteristic = resolve(self.inner.services().await?, &characteristic)?;
+                self.inner.read_characteristic(characteristic).await
+            })
             .await
-            .map_err(Into::into)
     }
 
     async fn write(
@@ -228,49 +781,481 @@ impl Peripheral {
         characteristic: Characteristic,
         data: Vec<u8>,
         write_type: WriteType,
+        options: Option<OperationOptions>,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
     ) -> Result<()> {
-        self.get_platform()
-            .await?
-            .write(&characteristic.into(), &data, write_type.into())
+        self.inner
+            .gatt
+            .run(options, cancellation_handle, async {
+                let characteristic = resolve(self.inner.services().await?, &characteristic)?;
+                self.inner
+                    .write_characteristic(characteristic, &data, write_type)
+                    .await
+            })
             .await
-            .map_err(Into::into)
     }
 
-    async fn read_descriptor(&self, descriptor: Descriptor) -> Result<Vec<u8>> {
-        self.get_platform()
-            .await?
-            .read_descriptor(&descriptor.into())
+    async fn read_descriptor(
+        &self,
+        descriptor: Descriptor,
+        options: Option<OperationOptions>,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<Vec<u8>> {
+        self.inner
+            .gatt
+            .run(options, cancellation_handle, async {
+                self.inner
+                    .get_platform()
+                    .await?
+                    .read_descriptor(&descriptor.into())
+                    .await
+                    .map_err(Into::into)
+            })
             .await
-            .map_err(Into::into)
     }
 
-    async fn write_descriptor(&self, descriptor: Descriptor, data: Vec<u8>) -> Result<()> {
-        self.get_platform()
-            .await?
-            .write_descriptor(&descriptor.into(), &data)
+    async fn write_descriptor(
+        &self,
+        descriptor: Descriptor,
+        data: Vec<u8>,
+        options: Option<OperationOptions>,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<()> {
+        self.inner
+            .gatt
+            .run(options, cancellation_handle, async {
+                self.inner
+                    .get_platform()
+                    .await?
+                    .write_descriptor(&descriptor.into(), &data)
+                    .await
+                    .map_err(Into::into)
+            })
             .await
-            .map_err(Into::into)
     }
 
-    async fn subscribe(&self, characteristic: Characteristic) -> Result<()> {
-        self.get_platform()
-            .await?
-            .subscribe(&characteristic.into())
+    /// Enables notifications or indications, returning the mode enabled (never
+    /// [`SubscriptionMode::Auto`]).
+    async fn subscribe(
+        &self,
+        characteristic: Characteristic,
+        mode: SubscriptionMode,
+        options: Option<OperationOptions>,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<SubscriptionMode> {
+        self.inner
+            .gatt
+            .run(options, cancellation_handle, async {
+                let _lock = self.inner.subscription_lock.lock().await;
+                let characteristic = resolve(self.inner.services().await?, &characteristic)?;
+                let mode = mode.resolve(characteristic.properties.clone().into())?;
+                self.inner
+                    .enable_notifications(characteristic.clone())
+                    .await?;
+                self.inner
+                    .subscriptions
+                    .lock()
+                    .unwrap()
+                    .insert(characteristic.instance, characteristic);
+                Ok(mode)
+            })
             .await
-            .map_err(Into::into)
     }
 
-    async fn unsubscribe(&self, characteristic: Characteristic) -> Result<()> {
-        self.get_platform()
-            .await?
-            .unsubscribe(&characteristic.into())
+    async fn unsubscribe(
+        &self,
+        characteristic: Characteristic,
+        options: Option<OperationOptions>,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<()> {
+        self.inner
+            .gatt
+            .run(options, cancellation_handle, async {
+                let _lock = self.inner.subscription_lock.lock().await;
+                self.inner
+                    .subscriptions
+                    .lock()
+                    .unwrap()
+                    .remove(&characteristic.instance);
+                // Still needed by observers.
+                if self.inner.observers.is_observed(characteristic.instance) {
+                    return Ok(());
+                }
+                let characteristic = resolve(self.inner.services().await?, &characteristic)?;
+                self.inner.disable_notifications(characteristic).await
+            })
             .await
-            .map_err(Into::into)
+    }
+
+    /// Delivers the values of `characteristic` to `callback` until the returned handle is cancelled
+    /// or dropped. Observers of the same characteristic share its notification subscription.
+    async fn observe(
+        &self,
+        characteristic: Characteristic,
+        callback: Box<dyn NotificationCallback>,
+        cancellation_handle: Option<Arc<CancellationHandle>>,
+    ) -> Result<Arc<SubscriptionHandle>> {
+        let key = self
+            .inner
+            .gatt
+            .run(None, cancellation_handle, async {
+                let _lock = self.inner.subscription_lock.lock().await;
+                let characteristic = resolve(self.inner.services().await?, &characteristic)?;
+                SubscriptionMode::Auto.resolve(characteristic.properties.clone().into())?;
+                let instance = characteristic.instance;
+                let (key, first) = self.inner.observers.add(characteristic.clone(), callback);
+                let subscribed = self
+                    .inner
+                    .subscriptions
+                    .lock()
+                    .unwrap()
+                    .contains_key(&instance);
+                if first
+                    && !subscribed
+                    && let Err(e) = self.inner.enable_notifications(characteristic).await
+                {
+                    let _ = self.inner.observers.remove(key);
+                    return Err(e);
+                }
+                Ok(key)
+            })
+            .await?;
+        Ok(Arc::new(SubscriptionHandle::new(
+            Arc::downgrade(&self.inner),
+            key,
+        )))
+    }
+}
+
+/// Finds `characteristic` in a rediscovered GATT `table`, where its instance may have changed (e.g.
+/// as services were added): by service and characteristic UUIDs, using the instance only to choose
+/// among characteristics sharing both.
+fn rediscover(table: &[Characteristic], characteristic: &Characteristic) -> Option<Characteristic> {
+    let candidates: Vec<_> = table
+        .iter()
+        .filter(|candidate| {
+            candidate.service == characteristic.service && candidate.uuid == characteristic.uuid
+        })
+        .collect();
+    match candidates.as_slice() {
+        [candidate] => Some((*candidate).clone()),
+        _ => candidates
+            .into_iter()
+            .find(|candidate| candidate.instance == characteristic.instance)
+            .cloned(),
+    }
+}
+
+/// Looks `characteristic` up in the GATT table by instance. Except on Linux, where operations go
+/// through BlueZ by instance (see [`crate::bluez::GattTable`]), btleplug addresses characteristics
+/// by service and characteristic UUIDs only: one sharing both with another characteristic cannot be
+/// targeted and is rejected rather than operating on whichever of them btleplug picks.
+fn resolve(services: Vec<Service>, characteristic: &Characteristic) -> Result<Characteristic> {
+    let candidates: Vec<_> = services
+        .into_iter()
+        .flat_map(|service| service.characteristics)
+        .filter(|candidate| {
+            candidate.service == characteristic.service && candidate.uuid == characteristic.uuid
+        })
+        .collect();
+    if cfg!(not(target_os = "linux")) && candidates.len() > 1 {
+        return Err(Error::NotSupported(format!(
+            "Several characteristics {} in service {}",
+            characteristic.uuid, characteristic.service
+        )));
+    }
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.instance == characteristic.instance)
+        .ok_or(Error::NoSuchCharacteristic)
+}
+
+/// Sets `state` (if currently `expected`, when given), notifying callbacks only on an actual change.
+fn update_state(
+    state: &Mutex<ConnectionState>,
+    callbacks: &dyn PeripheralCallbacks,
+    expected: Option<&ConnectionState>,
+    new: ConnectionState,
+) {
+    {
+        let mut current = state.lock().unwrap();
+        if *current == new || expected.is_some_and(|expected| *current != *expected) {
+            return;
+        }
+        *current = new.clone();
+    }
+    callbacks.state_changed(new);
+}
+
+/// Settles a transient state (e.g. [`ConnectionState::DiscoveringServices`]) that its operation did
+/// not move on from, whether it failed or was dropped (e.g. cancelled from Kotlin): to `Ready` if
+/// still connected, `Disconnected` otherwise. Leaves the state alone if something else (e.g. a
+/// disconnect event) changed it in the meantime.
+struct SettleState {
+    transient: Option<ConnectionState>,
+    state: Arc<Mutex<ConnectionState>>,
+    callbacks: Arc<Box<dyn PeripheralCallbacks>>,
+    platform: btleplug::platform::Peripheral,
+    reason: DisconnectReason,
+}
+
+impl SettleState {
+    /// Moves on from the transient state once its operation succeeded.
+    fn complete(mut self, state: ConnectionState) {
+        if let Some(transient) = self.transient.take() {
+            update_state(&self.state, &**self.callbacks, Some(&transient), state);
+        }
+    }
+}
+
+impl Drop for SettleState {
+    fn drop(&mut self) {
+        let Some(transient) = self.transient.take() else {
+            return;
+        };
+        let state = self.state.clone();
+        let callbacks = self.callbacks.clone();
+        let platform = self.platform.clone();
+        let reason = self.reason.clone();
+        let settle = async move {
+            let settled = match platform.is_connected().await {
+                Ok(true) => ConnectionState::Ready,
+                _ => ConnectionState::Disconnected {
+                    reason: Some(reason),
+                },
+            };
+            update_state(&state, &**callbacks, Some(&transient), settled);
+        };
+        // Cannot fail: peripherals are only built once the shared runtime is.
+        if let Ok(handle) = crate::runtime::handle() {
+            handle.spawn(settle);
+        }
+    }
+}
+
+async fn deliver(
+    callbacks: Arc<Box<dyn PeripheralCallbacks>>,
+    observers: Arc<Observers>,
+    sources: Vec<Characteristic>,
+    delivery: Delivery,
+) {
+    match delivery {
+        Delivery::Single(received) => {
+            let value = TimestampedValue::from(received);
+            for characteristic in sources {
+                for observer in observers.callbacks(characteristic.instance) {
+                    observer.notification(value.clone()).await;
+                }
+                callbacks.notification(characteristic, value.clone()).await;
+            }
+        }
+        Delivery::Batch(_, batch) => {
+            let values: Vec<TimestampedValue> = batch.into_iter().map(Into::into).collect();
+            for characteristic in sources {
+                for observer in observers.callbacks(characteristic.instance) {
+                    observer.notification_batch(values.clone()).await;
+                }
+                callbacks
+                    .notification_batch(characteristic, values.clone())
+                    .await;
+            }
+        }
+    }
+}
+
+/// Attributes a notification to the subscribed (or observed) characteristic it came from, falling
+/// back to the notifiable characteristics of the GATT table. Notifications identified by UUIDs only
+/// (see [`Source`]) are unambiguous among subscriptions, as [`resolve`] rejects duplicates there,
+/// but are dropped rather than guessed at when several characteristics of the table match.
+fn notification_sources(
+    subscriptions: &Mutex<HashMap<u32, Characteristic>>,
+    observers: &Observers,
+    services: impl FnOnce() -> Vec<Service>,
+    source: Source,
+) -> Vec<Characteristic> {
+    let matches = |characteristic: &Characteristic| match source {
+        Source::Uuids(service, uuid) => {
+            characteristic.service == Uuid::from(service) && characteristic.uuid == Uuid::from(uuid)
+        }
+        Source::Instance(instance) => characteristic.instance == instance,
+    };
+    let mut subscribed = subscriptions.lock().unwrap().clone();
+    for characteristic in observers.characteristics() {
+        subscribed.insert(characteristic.instance, characteristic);
+    }
+    let subscribed: Vec<_> = subscribed.into_values().filter(matches).collect();
+    if !subscribed.is_empty() {
+        return subscribed;
+    }
+    let candidates: Vec<_> = services()
+        .into_iter()
+        .flat_map(|service| service.characteristics)
+        .filter(|characteristic| {
+            let properties = CharPropFlags::from(characteristic.properties.clone());
+            matches(characteristic)
+                && properties.intersects(CharPropFlags::NOTIFY | CharPropFlags::INDICATE)
+        })
+        .collect();
+    if candidates.len() == 1 {
+        candidates
+    } else {
+        Vec::new()
+    }
+}
+
+async fn reconnect(peripheral: Weak<Inner>, policy: ReconnectPolicy, token: CancellationToken) {
+    let notify = |event| {
+        if let Some(peripheral) = peripheral.upgrade() {
+            peripheral.callbacks.reconnect(event);
+        }
+    };
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        if !policy.allows(attempt) {
+            notify(ReconnectEvent::GaveUp {
+                attempts: attempt - 1,
+            });
+            return;
+        }
+
+        let delay = policy.backoff(attempt);
+        notify(ReconnectEvent::Scheduled { attempt, delay });
+        tokio::select! {
+            _ = token.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        if policy.only_while_adapter_powered {
+            tokio::select! {
+                _ = token.cancelled() => return,
+                _ = wait_for_adapter_powered() => {}
+            }
+        }
+
+        // Not the `Peripheral` itself, so dropping it still cancels `token`, ending the attempt.
+        let Some(strong) = peripheral.upgrade() else {
+            return;
+        };
+        let handle = Arc::new(CancellationHandle::from_token(token.clone()));
+        let connection = async {
+            strong.establish_connection(handle.clone()).await?;
+            strong.discover_services(Some(handle)).await
+        };
+        let result = match timeout(policy.attempt_timeout, connection).await {
+            Ok(result) => result,
+            Err(_) => {
+                strong.abort_connection().await;
+                Err(Error::TimedOut(policy.attempt_timeout))
+            }
+        };
+        match result {
+            Ok(()) => {
+                notify(ReconnectEvent::Succeeded { attempt });
+                return;
+            }
+            Err(Error::Cancelled) => {
+                strong.settle_connect_phase(DisconnectReason::LocalRequest);
+                return;
+            }
+            Err(error) => {
+                strong.set_state(ConnectionState::Disconnected {
+                    reason: Some(DisconnectReason::ConnectionFailed),
+                });
+                notify(ReconnectEvent::Failed { attempt, error });
+            }
+        }
+    }
+}
+
+async fn wait_for_adapter_powered() {
+    let Ok((_registration, mut events)) = events::register_global().await else {
+        return;
+    };
+    let Ok(adapter) = get_adapter().await else {
+        return;
+    };
+    if matches!(adapter.adapter_state().await, Ok(CentralState::PoweredOn)) {
+        return;
+    }
+    while let Some(event) = events.recv().await {
+        if let CentralEvent::StateUpdate(CentralState::PoweredOn) = event {
+            return;
+        }
     }
 }
 
 impl Drop for Peripheral {
     fn drop(&mut self) {
-        self.cancellation.cancel();
+        self.inner.cancellation.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICE: uuid::Uuid = btleplug::api::bleuuid::uuid_from_u16(0x180F);
+    const LEVEL: uuid::Uuid = btleplug::api::bleuuid::uuid_from_u16(0x2A19);
+
+    /// Two Battery Services, each with its own Battery Level characteristic.
+    fn services() -> Vec<Service> {
+        [0x10, 0x20]
+            .into_iter()
+            .map(|instance| Service {
+                uuid: SERVICE.into(),
+                primary: true,
+                characteristics: vec![Characteristic {
+                    uuid: LEVEL.into(),
+                    service: SERVICE.into(),
+                    instance,
+                    properties: (CharPropFlags::READ | CharPropFlags::NOTIFY).into(),
+                    descriptors: Vec::new(),
+                }],
+            })
+            .collect()
+    }
+
+    fn instances(sources: Vec<Characteristic>) -> Vec<u32> {
+        sources.iter().map(|source| source.instance).collect()
+    }
+
+    #[test]
+    fn notifications_identified_by_instance_reach_that_duplicate() {
+        let subscriptions = Mutex::new(HashMap::new());
+        let observers = Observers::default();
+        let sources =
+            notification_sources(&subscriptions, &observers, services, Source::Instance(0x20));
+        assert_eq!(instances(sources), [0x20]);
+
+        let second = services()[1].characteristics[0].clone();
+        subscriptions.lock().unwrap().insert(0x20, second);
+        let sources =
+            notification_sources(&subscriptions, &observers, services, Source::Instance(0x20));
+        assert_eq!(instances(sources), [0x20]);
+    }
+
+    #[test]
+    fn notifications_identified_by_uuids_are_not_credited_to_a_guessed_duplicate() {
+        let subscriptions = Mutex::new(HashMap::new());
+        let observers = Observers::default();
+        let source = Source::Uuids(SERVICE, LEVEL);
+        assert!(notification_sources(&subscriptions, &observers, services, source).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn duplicates_are_resolved_by_instance() {
+        let second = services()[1].characteristics[0].clone();
+        assert_eq!(resolve(services(), &second).unwrap().instance, 0x20);
+
+        let missing = Characteristic {
+            instance: 0x30,
+            ..second
+        };
+        assert!(matches!(
+            resolve(services(), &missing),
+            Err(Error::NoSuchCharacteristic)
+        ));
     }
 }
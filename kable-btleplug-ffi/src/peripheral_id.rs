@@ -1,73 +1,255 @@
-#[cfg(target_os = "windows")]
+use crate::Result;
+use crate::address_type::AddressType;
 use btleplug::api::BDAddr;
-#[cfg(target_os = "linux")]
-use serde::{Deserialize, Serialize};
 use std::fmt::Display;
-#[cfg(target_os = "windows")]
+use std::hash::{Hash, Hasher};
+#[cfg(not(target_os = "macos"))]
 use std::str::FromStr;
 
-#[derive(Eq, PartialEq, Hash, Clone, uniffi::Object)]
+/// Identifies a peripheral. The string form is stable and round-trips through
+/// [`PeripheralId::new`]:
+///
+/// - Apple: the CoreBluetooth UUID, e.g. `4e5a4a48-5f6c-4b1d-9a36-6f3c7f1b2a90`.
+/// - Linux: the BlueZ object path relative to `/org/bluez`, e.g. `hci0/dev_AA_BB_CC_DD_EE_FF`.
+///   The absolute object path, and the JSON form emitted by earlier versions, are also accepted.
+/// - Windows: the Bluetooth address, e.g. `AA:BB:CC:DD:EE:FF`.
+#[derive(Clone, uniffi::Object)]
 #[uniffi::export(Display, Hash, Eq)]
 pub struct PeripheralId {
     pub platform: btleplug::platform::PeripheralId,
+
+    /// Present when built from a Bluetooth address, in which case a connection may be established
+    /// directly (without scanning) on platforms that support it.
+    pub address_type: Option<AddressType>,
 }
 
-#[cfg(target_os = "macos")]
-#[uniffi::export]
+#[uniffi::export(async_runtime = "tokio")]
 impl PeripheralId {
     #[uniffi::constructor]
-    fn new(value: String) -> Self {
-        Self {
-            platform: uuid::Uuid::parse_str(&value).unwrap().into(),
-        }
+    pub fn new(value: String) -> Result<Self> {
+        Ok(parse(value.trim())?.into())
     }
-}
 
-#[cfg(target_os = "macos")]
-impl Display for PeripheralId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.platform)
+    /// Builds an ID for a device that has not necessarily been discovered (e.g. a bonded device, or
+    /// an address read from a QR code). Not supported on Apple platforms, which hide addresses.
+    #[uniffi::constructor]
+    pub async fn from_address(address: String, address_type: AddressType) -> Result<Self> {
+        let address = BDAddr::from_str_delim(address.trim())
+            .map_err(|e| crate::Error::InvalidBDAddr(format!("{address}: {e}")))?;
+        Ok(Self {
+            platform: from_address(address).await?,
+            address_type: Some(address_type),
+        })
+    }
+
+    /// Absolute BlueZ object path of the device; only available on Linux.
+    pub fn object_path(&self) -> Option<String> {
+        object_path(&self.platform)
+    }
+
+    /// Bluetooth address of the device; not available on Apple platforms.
+    pub fn address(&self) -> Option<String> {
+        self.bd_addr().map(|address| address.to_string())
     }
 }
 
-#[cfg(target_os = "linux")]
-#[uniffi::export]
 impl PeripheralId {
-    #[uniffi::constructor]
-    fn new(value: String) -> Self {
-        Self {
-            platform: serde_json::from_str(&value).unwrap(),
-        }
+    pub(crate) fn bd_addr(&self) -> Option<BDAddr> {
+        address(&self.platform)
     }
 }
 
+#[cfg(target_os = "macos")]
+fn parse(value: &str) -> Result<btleplug::platform::PeripheralId> {
+    uuid::Uuid::parse_str(value)
+        .map(Into::into)
+        .map_err(|e| crate::Error::Uuid(format!("{value}: {e}")))
+}
+
+#[cfg(target_os = "macos")]
+async fn from_address(_address: BDAddr) -> Result<btleplug::platform::PeripheralId> {
+    Err(crate::Error::NotSupported(
+        "Bluetooth addresses are not exposed by CoreBluetooth".to_string(),
+    ))
+}
+
+#[cfg(target_os = "macos")]
+fn object_path(_platform: &btleplug::platform::PeripheralId) -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn address(_platform: &btleplug::platform::PeripheralId) -> Option<BDAddr> {
+    None
+}
+
 #[cfg(target_os = "linux")]
-impl Display for PeripheralId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", serde_json::to_string(&self.platform).unwrap())
-    }
+const BLUEZ_PREFIX: &str = "/org/bluez/";
+
+#[cfg(target_os = "linux")]
+fn parse(value: &str) -> Result<btleplug::platform::PeripheralId> {
+    use crate::Error;
+
+    let path = if value.starts_with('{') {
+        let legacy: serde_json::Value =
+            serde_json::from_str(value).map_err(|e| Error::Other(e.to_string()))?;
+        legacy["object_path"]
+            .as_str()
+            .ok_or_else(|| Error::Other(format!("Missing object path: {value}")))?
+            .to_string()
+    } else if value.starts_with(BLUEZ_PREFIX) {
+        value.to_string()
+    } else {
+        format!("{BLUEZ_PREFIX}{value}")
+    };
+
+    let device = path
+        .strip_prefix(BLUEZ_PREFIX)
+        .and_then(|relative| relative.split_once('/'))
+        .filter(|(adapter, _)| !adapter.is_empty())
+        .and_then(|(_, device)| device.strip_prefix("dev_"))
+        .ok_or_else(|| Error::Other(format!("Not a BlueZ device path: {value}")))?;
+    BDAddr::from_str(&device.replace('_', ":"))
+        .map_err(|e| Error::InvalidBDAddr(format!("{value}: {e}")))?;
+
+    serde_json::from_value(serde_json::json!({ "object_path": path }))
+        .map_err(|e| Error::Other(e.to_string()))
+}
+
+/// Device paths live under the adapter that was (or will be) used to reach them; use ours.
+#[cfg(target_os = "linux")]
+async fn from_address(address: BDAddr) -> Result<btleplug::platform::PeripheralId> {
+    let adapter = crate::bluez::adapter_path().await?;
+    let device = address.to_string().replace(':', "_");
+    parse(&format!("{adapter}/dev_{device}"))
+}
+
+#[cfg(target_os = "linux")]
+fn object_path(platform: &btleplug::platform::PeripheralId) -> Option<String> {
+    Some(format!("{BLUEZ_PREFIX}{platform}"))
+}
+
+#[cfg(target_os = "linux")]
+fn address(platform: &btleplug::platform::PeripheralId) -> Option<BDAddr> {
+    let path = platform.to_string();
+    let (_, device) = path.rsplit_once("/dev_")?;
+    BDAddr::from_str(&device.replace('_', ":")).ok()
 }
 
 #[cfg(target_os = "windows")]
-#[uniffi::export]
-impl PeripheralId {
-    #[uniffi::constructor]
-    fn new(value: String) -> Self {
-        PeripheralId {
-            platform: BDAddr::from_str(&value).unwrap().into(),
-        }
-    }
+fn parse(value: &str) -> Result<btleplug::platform::PeripheralId> {
+    BDAddr::from_str(value)
+        .map(Into::into)
+        .map_err(|e| crate::Error::InvalidBDAddr(format!("{value}: {e}")))
+}
+
+#[cfg(target_os = "windows")]
+async fn from_address(address: BDAddr) -> Result<btleplug::platform::PeripheralId> {
+    Ok(address.into())
+}
+
+#[cfg(target_os = "windows")]
+fn object_path(_platform: &btleplug::platform::PeripheralId) -> Option<String> {
+    None
 }
 
 #[cfg(target_os = "windows")]
+fn address(platform: &btleplug::platform::PeripheralId) -> Option<BDAddr> {
+    BDAddr::from_str(&platform.to_string()).ok()
+}
+
 impl Display for PeripheralId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.platform.to_string())
+        write!(f, "{}", self.platform)
+    }
+}
+
+// Equality ignores `address_type`, so that an ID built from an address matches the one reported
+// once the device is discovered.
+impl PartialEq for PeripheralId {
+    fn eq(&self, other: &Self) -> bool {
+        self.platform == other.platform
+    }
+}
+
+impl Eq for PeripheralId {}
+
+impl Hash for PeripheralId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.platform.hash(state);
     }
 }
 
 impl From<btleplug::platform::PeripheralId> for PeripheralId {
     fn from(platform: btleplug::platform::PeripheralId) -> Self {
-        Self { platform }
+        Self {
+            platform,
+            address_type: None,
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    const CANONICAL: &str = "hci0/dev_AA_BB_CC_DD_EE_FF";
+
+    #[test]
+    fn canonical_form_round_trips() {
+        let id = PeripheralId::new(CANONICAL.to_string()).unwrap();
+        assert_eq!(id.to_string(), CANONICAL);
+        assert!(PeripheralId::new(id.to_string()).unwrap() == id);
+    }
+
+    #[test]
+    fn object_path_and_legacy_json_parse_to_canonical_form() {
+        let path = "/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF";
+        let legacy = r#"{"object_path":"/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF"}"#;
+        for value in [path, legacy] {
+            assert_eq!(
+                PeripheralId::new(value.to_string()).unwrap().to_string(),
+                CANONICAL,
+            );
+        }
+    }
+
+    #[test]
+    fn accessors() {
+        let id = PeripheralId::new(CANONICAL.to_string()).unwrap();
+        assert_eq!(
+            id.object_path().as_deref(),
+            Some("/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF"),
+        );
+        assert_eq!(id.address().as_deref(), Some("AA:BB:CC:DD:EE:FF"));
+    }
+
+    #[test]
+    fn address_type_does_not_affect_equality() {
+        let id = PeripheralId::new(CANONICAL.to_string()).unwrap();
+        let directed = PeripheralId {
+            address_type: Some(AddressType::Random),
+            ..id.clone()
+        };
+        assert!(directed == id);
+    }
+
+    #[test]
+    fn invalid_address_is_rejected() {
+        assert!(matches!(
+            PeripheralId::new("hci0/dev_AA_BB_CC".to_string()),
+            Err(Error::InvalidBDAddr(_)),
+        ));
+    }
+
+    #[test]
+    fn invalid_path_is_rejected() {
+        for value in ["", "hci0", "/org/bluez/hci0", "{}", "not json {"] {
+            assert!(matches!(
+                PeripheralId::new(value.to_string()),
+                Err(Error::Other(_)),
+            ));
+        }
     }
 }
@@ -1,3 +1,4 @@
+use crate::class_of_device::{ClassOfDevice, decode_class_of_device};
 use crate::uuid::Uuid;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -13,6 +14,7 @@ pub struct PeripheralProperties {
     pub service_data: HashMap<Uuid, Vec<u8>>,
     pub services: Vec<Uuid>,
     pub class: Option<u32>,
+    pub class_of_device: Option<ClassOfDevice>,
 }
 
 impl PeripheralProperties {
@@ -33,6 +35,7 @@ impl PeripheralProperties {
                 .collect(),
             services: platform.services.into_iter().map(Into::into).collect(),
             class: platform.class,
+            class_of_device: platform.class.map(decode_class_of_device),
         }
     }
 }
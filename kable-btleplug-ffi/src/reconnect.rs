@@ -0,0 +1,110 @@
+use crate::Error;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::time::Duration;
+
+/// Opt-in policy for reconnecting automatically after an unexpected disconnect.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct ReconnectPolicy {
+    /// Maximum number of attempts per disconnect; `0` retries indefinitely.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts, jitter included.
+    pub max_backoff: Duration,
+    /// Factor applied to the backoff after each failed attempt.
+    pub multiplier: f64,
+    /// Fraction (`0.0..=1.0`) by which each backoff is randomly shortened or lengthened.
+    pub jitter: f64,
+    /// Wait for the adapter to be powered on before attempting (and counting) an attempt.
+    pub only_while_adapter_powered: bool,
+    /// Time limit on each attempt (rescanning for the device, connecting and discovering services),
+    /// after which it fails with [`Error::TimedOut`] and the next one is scheduled. Rescanning does
+    /// not end on its own while the device is out of range.
+    pub attempt_timeout: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Delay before the given (1-based) attempt.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        let max = self.max_backoff.as_secs_f64();
+        let jitter = self.jitter.clamp(0.0, 1.0) * (2.0 * random_unit() - 1.0);
+        // `min` also discards NaN (e.g. a NaN jitter or an infinite multiplier on a zero backoff).
+        let delay = (backoff.min(max) * (1.0 + jitter)).min(max).max(0.0);
+        Duration::try_from_secs_f64(delay).unwrap_or(self.max_backoff)
+    }
+
+    pub(crate) fn allows(&self, attempt: u32) -> bool {
+        self.max_attempts == 0 || attempt <= self.max_attempts
+    }
+}
+
+#[derive(Debug, uniffi::Enum)]
+pub enum ReconnectEvent {
+    Scheduled { attempt: u32, delay: Duration },
+    Failed { attempt: u32, error: Error },
+    Succeeded { attempt: u32 },
+    GaveUp { attempts: u32 },
+}
+
+/// Uniformly distributed value in `0.0..1.0`, from the randomly seeded standard library hasher.
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(multiplier: f64, jitter: f64) -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_attempts: 0,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            multiplier,
+            jitter,
+            only_while_adapter_powered: false,
+            attempt_timeout: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn backoff_grows_by_multiplier() {
+        let policy = policy(2.0, 0.0);
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(6), Duration::from_secs(32));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = policy(2.0, 0.0);
+        assert_eq!(policy.backoff(7), Duration::from_secs(60));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn extreme_values_do_not_panic() {
+        let mut policy = policy(f64::MAX, 1.0);
+        policy.max_backoff = Duration::MAX;
+        assert!(policy.backoff(u32::MAX) <= Duration::MAX);
+
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::ZERO,
+            ..self::policy(f64::INFINITY, f64::NAN)
+        };
+        assert!(policy.backoff(u32::MAX) <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = policy(2.0, 0.5);
+        for _ in 0..1000 {
+            let delay = policy.backoff(3);
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(6));
+            assert!(policy.backoff(10) <= Duration::from_secs(60));
+        }
+    }
+}
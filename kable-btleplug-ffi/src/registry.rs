@@ -0,0 +1,282 @@
+use crate::peripheral_id::PeripheralId;
+use crate::peripheral_properties::PeripheralProperties;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Most devices remembered at once; the least recently seen device is forgotten to make room.
+const CAPACITY: usize = 1024;
+
+/// BLE's minimum advertising interval. Platforms report one advertisement as several property
+/// changes (RSSI, manufacturer data, service data...) in quick succession, so updates closer together
+/// than this are taken as the same advertisement.
+const MIN_ADVERTISING_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Every peripheral seen while scanning, keyed by ID. Survives across scans until cleared, or until
+/// evicted to stay within [`CAPACITY`].
+static REGISTRY: LazyLock<Mutex<HashMap<PeripheralId, Entry>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Clone, uniffi::Record)]
+pub struct KnownDevice {
+    pub id: Arc<PeripheralId>,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    pub properties: PeripheralProperties,
+    pub advertisement_count: u64,
+    /// Shortest gap observed between two consecutive advertisements. Missed advertisements only
+    /// ever lengthen the observed gap, so this is the closest estimate of the advertising interval.
+    pub advertising_interval: Option<Duration>,
+    pub rssi: Option<RssiStatistics>,
+}
+
+#[derive(Clone, uniffi::Record)]
+pub struct RssiStatistics {
+    pub min: i16,
+    pub max: i16,
+    pub mean: f64,
+}
+
+struct Entry {
+    first_seen: SystemTime,
+    last_seen: SystemTime,
+    /// When the last counted advertisement was received.
+    last_advertisement: Instant,
+    properties: PeripheralProperties,
+    advertisement_count: u64,
+    advertising_interval: Option<Duration>,
+    rssi_min: i16,
+    rssi_max: i16,
+    rssi_sum: i64,
+    rssi_count: u64,
+}
+
+impl Entry {
+    fn new(properties: PeripheralProperties, now: Instant) -> Self {
+        let mut entry = Self {
+            first_seen: SystemTime::now(),
+            last_seen: SystemTime::now(),
+            last_advertisement: now,
+            properties: properties.clone(),
+            advertisement_count: 1,
+            advertising_interval: None,
+            rssi_min: i16::MAX,
+            rssi_max: i16::MIN,
+            rssi_sum: 0,
+            rssi_count: 0,
+        };
+        entry.record(properties);
+        entry
+    }
+
+    fn update(&mut self, properties: PeripheralProperties, now: Instant) {
+        let gap = now.duration_since(self.last_advertisement);
+        if gap >= MIN_ADVERTISING_INTERVAL {
+            self.advertising_interval = Some(self.advertising_interval.map_or(gap, |i| i.min(gap)));
+            self.advertisement_count += 1;
+            self.last_advertisement = now;
+        }
+        self.last_seen = SystemTime::now();
+        self.record(properties);
+    }
+
+    fn record(&mut self, properties: PeripheralProperties) {
+        if let Some(rssi) = properties.rssi {
+            self.rssi_min = self.rssi_min.min(rssi);
+            self.rssi_max = self.rssi_max.max(rssi);
+            self.rssi_sum += i64::from(rssi);
+            self.rssi_count += 1;
+        }
+        self.properties = properties;
+    }
+
+    fn known_device(&self, id: &PeripheralId) -> KnownDevice {
+        KnownDevice {
+            id: Arc::new(id.clone()),
+            first_seen: self.first_seen,
+            last_seen: self.last_seen,
+            properties: self.properties.clone(),
+            advertisement_count: self.advertisement_count,
+            advertising_interval: self.advertising_interval,
+            rssi: (self.rssi_count > 0).then(|| RssiStatistics {
+                min: self.rssi_min,
+                max: self.rssi_max,
+                mean: self.rssi_sum as f64 / self.rssi_count as f64,
+            }),
+        }
+    }
+}
+
+/// Records an advertisement (or properties update) received while scanning.
+pub(crate) fn record(properties: &PeripheralProperties) {
+    insert(&mut REGISTRY.lock().unwrap(), properties, Instant::now());
+}
+
+fn insert(
+    registry: &mut HashMap<PeripheralId, Entry>,
+    properties: &PeripheralProperties,
+    now: Instant,
+) {
+    match registry.get_mut(&properties.id) {
+        Some(entry) => entry.update(properties.clone(), now),
+        None => {
+            if registry.len() >= CAPACITY {
+                evict_least_recently_seen(registry);
+            }
+            registry.insert(
+                (*properties.id).clone(),
+                Entry::new(properties.clone(), now),
+            );
+        }
+    }
+}
+
+fn evict_least_recently_seen(registry: &mut HashMap<PeripheralId, Entry>) {
+    let oldest = registry
+        .iter()
+        .min_by_key(|(_, entry)| entry.last_seen)
+        .map(|(id, _)| id.clone());
+    if let Some(id) = oldest {
+        registry.remove(&id);
+    }
+}
+
+/// Services the device advertised when last seen, if it was seen at all.
+pub(crate) fn advertised_services(id: &PeripheralId) -> Vec<uuid::Uuid> {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .get(id)
+        .map_or_else(Vec::new, |entry| {
+            entry
+                .properties
+                .services
+                .iter()
+                .copied()
+                .map(Into::into)
+                .collect()
+        })
+}
+
+/// Returns every known device, most recently seen first.
+#[uniffi::export]
+pub fn registry_snapshot() -> Vec<KnownDevice> {
+    let registry = REGISTRY.lock().unwrap();
+    let mut devices: Vec<_> = registry
+        .iter()
+        .map(|(id, entry)| entry.known_device(id))
+        .collect();
+    devices.sort_by_key(|device| Reverse(device.last_seen));
+    devices
+}
+
+#[uniffi::export]
+pub fn registry_get(id: Arc<PeripheralId>) -> Option<KnownDevice> {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .get(&id)
+        .map(|entry| entry.known_device(&id))
+}
+
+#[uniffi::export]
+pub fn registry_clear() {
+    REGISTRY.lock().unwrap().clear();
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn id(n: usize) -> Arc<PeripheralId> {
+        let path = format!("hci0/dev_AA_BB_CC_DD_{:02X}_{:02X}", n / 256, n % 256);
+        Arc::new(PeripheralId::new(path).unwrap())
+    }
+
+    fn advertisement(id: &Arc<PeripheralId>, rssi: Option<i16>) -> PeripheralProperties {
+        PeripheralProperties {
+            id: id.clone(),
+            local_name: None,
+            tx_power_level: None,
+            rssi,
+            manufacturer_data: HashMap::new(),
+            service_data: HashMap::new(),
+            services: Vec::new(),
+            class: None,
+            class_of_device: None,
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn updates_within_the_minimum_interval_are_one_advertisement() {
+        let start = Instant::now();
+        let id = id(0);
+        let mut entry = Entry::new(advertisement(&id, None), start);
+        entry.update(advertisement(&id, None), start + ms(5));
+        entry.update(advertisement(&id, None), start + ms(19));
+        let device = entry.known_device(&id);
+        assert_eq!(device.advertisement_count, 1);
+        assert_eq!(device.advertising_interval, None);
+
+        // Measured from the first update of the advertisement, not the last.
+        entry.update(advertisement(&id, None), start + ms(20));
+        let device = entry.known_device(&id);
+        assert_eq!(device.advertisement_count, 2);
+        assert_eq!(device.advertising_interval, Some(ms(20)));
+    }
+
+    #[test]
+    fn advertising_interval_is_the_shortest_gap() {
+        let start = Instant::now();
+        let id = id(0);
+        let mut entry = Entry::new(advertisement(&id, None), start);
+        for at in [300, 400, 1000] {
+            entry.update(advertisement(&id, None), start + ms(at));
+        }
+        let device = entry.known_device(&id);
+        assert_eq!(device.advertisement_count, 4);
+        assert_eq!(device.advertising_interval, Some(ms(100)));
+    }
+
+    #[test]
+    fn rssi_statistics_skip_updates_without_rssi() {
+        let start = Instant::now();
+        let id = id(0);
+        let mut entry = Entry::new(advertisement(&id, Some(-70)), start);
+        assert!(entry.known_device(&id).rssi.is_some());
+        entry.update(advertisement(&id, None), start + ms(100));
+        entry.update(advertisement(&id, Some(-50)), start + ms(200));
+        entry.update(advertisement(&id, Some(-90)), start + ms(300));
+        let rssi = entry.known_device(&id).rssi.unwrap();
+        assert_eq!((rssi.min, rssi.max), (-90, -50));
+        assert_eq!(rssi.mean, -70.0);
+
+        let entry = Entry::new(advertisement(&id, None), start);
+        assert!(entry.known_device(&id).rssi.is_none());
+    }
+
+    #[test]
+    fn least_recently_seen_device_is_evicted_at_capacity() {
+        let now = Instant::now();
+        let mut registry = HashMap::new();
+        for n in 0..CAPACITY {
+            insert(&mut registry, &advertisement(&id(n), None), now);
+        }
+        registry.get_mut(&*id(7)).unwrap().last_seen = SystemTime::UNIX_EPOCH;
+
+        insert(&mut registry, &advertisement(&id(CAPACITY), None), now);
+        assert_eq!(registry.len(), CAPACITY);
+        assert!(!registry.contains_key(&*id(7)));
+        assert!(registry.contains_key(&*id(CAPACITY)));
+
+        // Known devices are updated in place.
+        insert(&mut registry, &advertisement(&id(0), None), now + ms(100));
+        assert_eq!(registry.len(), CAPACITY);
+        assert_eq!(registry[&*id(0)].advertisement_count, 2);
+    }
+}
@@ -0,0 +1,85 @@
+//! Runtime shared by every background task (scans, adapter event pump, notification delivery), so
+//! that thread usage stays bounded regardless of the number of scans and peripherals.
+
+use crate::{Error, Result};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tokio::runtime::{Handle, Runtime};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct RuntimeConfig {
+    /// Number of worker threads; `0` uses one per CPU core.
+    pub worker_threads: u32,
+    /// Worker threads are named `{thread_name_prefix}-{n}`.
+    pub thread_name_prefix: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: 2,
+            thread_name_prefix: "kable-btleplug".to_string(),
+        }
+    }
+}
+
+static CONFIG: Mutex<Option<RuntimeConfig>> = Mutex::new(None);
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Configures the shared runtime. Must be called before any other function of this library, as the
+/// runtime is built on first use; fails with [`Error::RuntimeError`] otherwise.
+#[uniffi::export]
+pub fn configure_runtime(config: RuntimeConfig) -> Result<()> {
+    let mut current = CONFIG.lock().unwrap();
+    if RUNTIME.get().is_some() {
+        return Err(Error::RuntimeError("Runtime already started".to_string()));
+    }
+    *current = Some(config);
+    Ok(())
+}
+
+/// Handle to the shared runtime, building it on first use.
+pub(crate) fn handle() -> Result<&'static Handle> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime.handle());
+    }
+    // Held while building, so that a concurrent `configure_runtime` cannot be silently ignored.
+    let config = CONFIG.lock().unwrap();
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime.handle());
+    }
+    let runtime = build(config.clone().unwrap_or_default())?;
+    Ok(RUNTIME.get_or_init(|| runtime).handle())
+}
+
+fn build(config: RuntimeConfig) -> Result<Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if config.worker_threads > 0 {
+        builder.worker_threads(config.worker_threads as usize);
+    }
+    let next_thread = AtomicUsize::new(0);
+    let prefix = config.thread_name_prefix;
+    builder
+        .thread_name_fn(move || format!("{prefix}-{}", next_thread.fetch_add(1, Ordering::Relaxed)))
+        .enable_all()
+        .build()
+        .map_err(|e| Error::RuntimeError(e.to_string()))
+}
+
+/// Spawns `future` on the shared runtime, dropping it once `token` is cancelled.
+pub(crate) fn spawn<F>(token: CancellationToken, future: F) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    handle()?.spawn(async move {
+        tokio::select! {
+            // Checked first, so that nothing more is done once cancelled.
+            biased;
+            _ = token.cancelled() => {}
+            _ = future => {}
+        }
+    });
+    Ok(())
+}
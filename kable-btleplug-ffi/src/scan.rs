@@ -1,9 +1,13 @@
+use crate::access_list;
 use crate::cancellation_handle::CancellationHandle;
+use crate::discovery::DiscoverySession;
+use crate::events;
 use crate::peripheral_properties::PeripheralProperties;
-use btleplug::api::{Central, CentralEvent, CentralState, Peripheral, ScanFilter};
+use crate::registry;
+use crate::{Result, runtime};
+use btleplug::api::{Central, CentralEvent, CentralState, Peripheral};
 use btleplug::platform::{Adapter, PeripheralId};
 use std::sync::Arc;
-use tokio_stream::StreamExt;
 use tokio_util::sync::CancellationToken;
 
 #[uniffi::export(callback_interface)]
@@ -13,47 +17,37 @@ pub trait ScanCallback: Send + Sync {
 }
 
 #[uniffi::export(async_runtime = "tokio")]
-pub async fn scan(callbacks: Box<dyn ScanCallback>) -> CancellationHandle {
+pub async fn scan(callbacks: Box<dyn ScanCallback>) -> Result<CancellationHandle> {
     let token = CancellationToken::new();
     let handle = CancellationHandle::from_token(token.clone());
 
-    let adapter = crate::get_adapter().await;
-    let mut events = adapter.events().await.unwrap();
-    adapter.start_scan(ScanFilter::default()).await.unwrap();
-    std::thread::spawn(move || {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .unwrap();
-
-        rt.block_on(async move {
-            loop {
-                tokio::select! {
-                    _ = token.cancelled() => {
-                        // May fail if the adapter is powered off (e.g. computer went to sleep);
-                        // ignore, as scanning has already stopped in that case.
-                        let _ = adapter.stop_scan().await;
-                        break;
-                    },
-                    Some(event) = events.next() => match event {
-                        CentralEvent::DeviceDiscovered(id) =>
-                            handle_event(&adapter, &*callbacks, id).await,
-                        CentralEvent::DeviceUpdated(id) =>
-                            handle_event(&adapter, &*callbacks, id).await,
-                        // The system stops scanning when the adapter powers off (e.g. computer
-                        // goes to sleep or Bluetooth is toggled off) and does not resume it when
-                        // the adapter powers back on. Restart scanning so that this scan resumes
-                        // emitting advertisements: https://github.com/JuulLabs/kable/issues/1152
-                        CentralEvent::StateUpdate(CentralState::PoweredOn) => {
-                            let _ = adapter.start_scan(ScanFilter::default()).await;
-                        }
-                        _ => {}
+    let adapter = crate::get_adapter().await?;
+    let (registration, mut events) = events::register_global().await?;
+    let discovery = DiscoverySession::start().await?;
+    runtime::handle()?.spawn(async move {
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                Some(event) = events.recv() => match event {
+                    CentralEvent::DeviceDiscovered(id) =>
+                        handle_event(&adapter, &*callbacks, id).await,
+                    CentralEvent::DeviceUpdated(id) =>
+                        handle_event(&adapter, &*callbacks, id).await,
+                    // The system stops scanning when the adapter powers off (e.g. computer
+                    // goes to sleep or Bluetooth is toggled off) and does not resume it when
+                    // the adapter powers back on. Restart scanning so that this scan resumes
+                    // emitting advertisements: https://github.com/JuulLabs/kable/issues/1152
+                    CentralEvent::StateUpdate(CentralState::PoweredOn) => {
+                        discovery.restart().await;
                     }
+                    _ => {}
                 }
             }
-        });
+        }
+        discovery.stop().await;
+        drop(registration);
     });
-    handle
+    Ok(handle)
 }
 
 async fn handle_event(adapter: &Adapter, callbacks: &dyn ScanCallback, id: PeripheralId) {
@@ -63,9 +57,14 @@ async fn handle_event(adapter: &Adapter, callbacks: &dyn ScanCallback, id: Perip
     let Ok(peripheral) = adapter.peripheral(&id).await else {
         return;
     };
+    let id = crate::peripheral_id::PeripheralId::from(id);
+    if !access_list::is_permitted(&id, peripheral.address()) {
+        return;
+    }
     let Ok(Some(properties)) = peripheral.properties().await else {
         return;
     };
-    let properties = PeripheralProperties::new(Arc::new(id.into()), properties);
+    let properties = PeripheralProperties::new(Arc::new(id), properties);
+    registry::record(&properties);
     callbacks.update(properties).await;
 }
@@ -18,12 +18,28 @@ impl From<Service> for btleplug::api::Service {
     }
 }
 
-impl From<btleplug::api::Service> for Service {
-    fn from(value: btleplug::api::Service) -> Self {
-        Self {
-            uuid: value.uuid.into(),
-            primary: value.primary,
-            characteristics: value.characteristics.into_iter().map(Into::into).collect(),
-        }
+impl Service {
+    /// Converts a peripheral's GATT table, numbering its characteristics in table order (see
+    /// [`Characteristic::instance`]).
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn from_platform(
+        services: impl IntoIterator<Item = btleplug::api::Service>,
+    ) -> Vec<Self> {
+        let mut instance = 0;
+        services
+            .into_iter()
+            .map(|service| Self {
+                uuid: service.uuid.into(),
+                primary: service.primary,
+                characteristics: service
+                    .characteristics
+                    .into_iter()
+                    .map(|characteristic| {
+                        instance += 1;
+                        Characteristic::new(characteristic, instance)
+                    })
+                    .collect(),
+            })
+            .collect()
     }
 }
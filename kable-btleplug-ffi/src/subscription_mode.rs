@@ -0,0 +1,74 @@
+use crate::{Error, Result};
+use btleplug::api::CharPropFlags;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, uniffi::Enum)]
+pub enum SubscriptionMode {
+    Notify,
+    Indicate,
+    /// Notifications if supported, indications otherwise.
+    Auto,
+}
+
+impl SubscriptionMode {
+    /// The mode enabled for a characteristic with the given properties.
+    ///
+    /// The platforms (BlueZ, WinRT and CoreBluetooth alike) enable notifications whenever the
+    /// characteristic supports them, so indications can only be enabled on characteristics that do
+    /// not also support notifications.
+    pub(crate) fn resolve(self, properties: CharPropFlags) -> Result<Self> {
+        let notify = properties.contains(CharPropFlags::NOTIFY);
+        let indicate = properties.contains(CharPropFlags::INDICATE);
+        match self {
+            Self::Notify | Self::Auto if notify => Ok(Self::Notify),
+            Self::Indicate | Self::Auto if indicate && !notify => Ok(Self::Indicate),
+            Self::Notify => Err(Error::NotSupported(
+                "Characteristic does not support notifications".to_string(),
+            )),
+            Self::Indicate if indicate => Err(Error::NotSupported(
+                "Indications cannot be selected on a characteristic also supporting notifications"
+                    .to_string(),
+            )),
+            Self::Indicate => Err(Error::NotSupported(
+                "Characteristic does not support indications".to_string(),
+            )),
+            Self::Auto => Err(Error::NotSupported(
+                "Characteristic supports neither notifications nor indications".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_prefers_notifications() {
+        let both = CharPropFlags::NOTIFY | CharPropFlags::INDICATE;
+        assert_eq!(
+            SubscriptionMode::Auto.resolve(both).ok(),
+            Some(SubscriptionMode::Notify)
+        );
+        assert_eq!(
+            SubscriptionMode::Auto.resolve(CharPropFlags::INDICATE).ok(),
+            Some(SubscriptionMode::Indicate)
+        );
+    }
+
+    #[test]
+    fn modes_the_platform_would_not_enable_are_rejected() {
+        let both = CharPropFlags::NOTIFY | CharPropFlags::INDICATE;
+        assert!(matches!(
+            SubscriptionMode::Indicate.resolve(both),
+            Err(Error::NotSupported(_))
+        ));
+        assert!(matches!(
+            SubscriptionMode::Notify.resolve(CharPropFlags::INDICATE),
+            Err(Error::NotSupported(_))
+        ));
+        assert!(matches!(
+            SubscriptionMode::Auto.resolve(CharPropFlags::READ),
+            Err(Error::NotSupported(_))
+        ));
+    }
+}
@@ -1,16 +1,165 @@
-#[derive(Clone, Eq, PartialEq, Hash)]
-pub struct Uuid(String);
+use crate::{Error, Result};
+use btleplug::api::bleuuid::{BleUuid, uuid_from_u16, uuid_from_u32};
+use std::fmt::Display;
+use std::str::FromStr;
 
-uniffi::custom_newtype!(Uuid, String);
+/// A validated UUID, crossing the FFI boundary as its lowercase hyphenated string form.
+///
+/// Accepts full 128-bit UUIDs as well as 16-bit (`180d`) and 32-bit (`0000180d`) short forms,
+/// optionally prefixed with `0x`; short forms are expanded using the Bluetooth Base UUID.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Uuid(uuid::Uuid);
+
+uniffi::custom_type!(Uuid, String, {
+    try_lift: |value| Ok(value.parse::<Uuid>()?),
+    lower: |uuid| uuid.to_string(),
+});
+
+impl Uuid {
+    pub fn new(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub fn from_u16(short: u16) -> Self {
+        Self(uuid_from_u16(short))
+    }
+
+    pub fn from_u32(short: u32) -> Self {
+        Self(uuid_from_u32(short))
+    }
+
+    /// Returns the 16-bit short form, if this UUID is derived from the Bluetooth Base UUID.
+    pub fn to_u16(&self) -> Option<u16> {
+        self.0.to_ble_u16()
+    }
+
+    /// Returns the 32-bit short form, if this UUID is derived from the Bluetooth Base UUID.
+    pub fn to_u32(&self) -> Option<u32> {
+        self.0.to_ble_u32()
+    }
+}
+
+impl FromStr for Uuid {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let short = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(value);
+        if matches!(short.len(), 4 | 8) && short.chars().all(|c| c.is_ascii_hexdigit()) {
+            // Cannot fail: at most 8 hex digits always fit in a `u32`.
+            return Ok(Self::from_u32(u32::from_str_radix(short, 16).unwrap()));
+        }
+        uuid::Uuid::parse_str(value)
+            .map(Self)
+            .map_err(|e| Error::Uuid(format!("{value}: {e}")))
+    }
+}
+
+impl Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
 impl From<Uuid> for uuid::Uuid {
     fn from(value: Uuid) -> Self {
-        Self::parse_str(&value.0).unwrap()
+        value.0
     }
 }
 
 impl From<uuid::Uuid> for Uuid {
     fn from(value: uuid::Uuid) -> Self {
-        Self(value.to_string())
+        Self(value)
+    }
+}
+
+/// Parses and normalizes a UUID string, expanding 16-bit and 32-bit short forms.
+#[uniffi::export]
+pub fn parse_uuid(value: String) -> Result<Uuid> {
+    Uuid::new(&value)
+}
+
+#[uniffi::export]
+pub fn uuid_from_short(short: u32) -> Uuid {
+    Uuid::from_u32(short)
+}
+
+#[uniffi::export]
+pub fn uuid_to_u16(uuid: Uuid) -> Option<u16> {
+    uuid.to_u16()
+}
+
+#[uniffi::export]
+pub fn uuid_to_u32(uuid: Uuid) -> Option<u32> {
+    uuid.to_u32()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEART_RATE: &str = "0000180d-0000-1000-8000-00805f9b34fb";
+
+    #[test]
+    fn short_forms_are_expanded() {
+        assert_eq!(Uuid::new("180d").unwrap().to_string(), HEART_RATE);
+        assert_eq!(Uuid::new("0000180d").unwrap().to_string(), HEART_RATE);
+        assert_eq!(
+            Uuid::new("12345678").unwrap().to_string(),
+            "12345678-0000-1000-8000-00805f9b34fb"
+        );
+    }
+
+    #[test]
+    fn hex_prefix_is_accepted() {
+        assert_eq!(Uuid::new("0x180d").unwrap().to_string(), HEART_RATE);
+        assert_eq!(Uuid::new("0X0000180D").unwrap().to_string(), HEART_RATE);
+    }
+
+    #[test]
+    fn case_is_normalized() {
+        assert_eq!(Uuid::new("180D").unwrap().to_string(), HEART_RATE);
+        assert_eq!(
+            Uuid::new("0000180D-0000-1000-8000-00805F9B34FB").unwrap(),
+            Uuid::new(HEART_RATE).unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_strings_are_rejected() {
+        for value in [
+            "",
+            "18",
+            "180g",
+            "0x",
+            "0000180d-0000-1000-8000",
+            "not a uuid",
+        ] {
+            assert!(
+                matches!(Uuid::new(value), Err(Error::Uuid(_))),
+                "{value:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn short_forms_round_trip() {
+        let uuid = Uuid::from_u16(0x180d);
+        assert_eq!(uuid.to_u16(), Some(0x180d));
+        assert_eq!(uuid.to_u32(), Some(0x180d));
+
+        let uuid = Uuid::from_u32(0x1234_5678);
+        assert_eq!(uuid.to_u32(), Some(0x1234_5678));
+        assert_eq!(uuid.to_u16(), None);
+    }
+
+    #[test]
+    fn custom_uuids_have_no_short_form() {
+        let uuid = Uuid::new("6e400001-b5a3-f393-e0a9-e50e24dcca9e").unwrap();
+        assert_eq!(uuid.to_u16(), None);
+        assert_eq!(uuid.to_u32(), None);
     }
 }
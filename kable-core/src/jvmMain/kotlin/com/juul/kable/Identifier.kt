@@ -2,7 +2,10 @@ package com.juul.kable
 
 import com.juul.kable.btleplug.ffi.PeripheralId
 
-/** Depending on the OS, this is either a UUID (Apple), MAC address (Windows), or path (Linux). */
+/**
+ * Depending on the OS, this is either a UUID (Apple), MAC address (Windows), or BlueZ object path relative to
+ * `/org/bluez` (Linux, e.g. `hci0/dev_AA_BB_CC_DD_EE_FF`).
+ */
 public actual class Identifier(internal val ffi: PeripheralId) {
     override fun equals(other: Any?): Boolean = other is Identifier && other.ffi == ffi
     override fun hashCode(): Int = ffi.hashCode()
@@ -1,6 +1,8 @@
 package com.juul.kable
 
 import com.juul.kable.btleplug.BtleplugPeripheral
+import com.juul.kable.btleplug.withCancellationHandle
+import com.juul.kable.btleplug.ffi.SubscriptionMode
 import kotlinx.coroutines.Dispatchers
 import kotlinx.coroutines.withContext
 
@@ -18,7 +20,18 @@ private class BtleplugObservationHandler(
             detail(characteristic)
         }
         withContext(Dispatchers.IO) {
-            peripheral.ffi.subscribe(peripheral.getCharacteristic(characteristic))
+            val mode = withCancellationHandle { handle ->
+                peripheral.ffi.subscribe(
+                    peripheral.getCharacteristic(characteristic),
+                    SubscriptionMode.AUTO,
+                    options = null,
+                    cancellationHandle = handle,
+                )
+            }
+            peripheral.logger.verbose {
+                message = "Enabled $mode"
+                detail(characteristic)
+            }
         }
     }
 
@@ -28,7 +41,13 @@ private class BtleplugObservationHandler(
             detail(characteristic)
         }
         withContext(Dispatchers.IO) {
-            peripheral.ffi.unsubscribe(peripheral.getCharacteristic(characteristic))
+            withCancellationHandle { handle ->
+                peripheral.ffi.unsubscribe(
+                    peripheral.getCharacteristic(characteristic),
+                    options = null,
+                    cancellationHandle = handle,
+                )
+            }
         }
     }
 }
@@ -15,17 +15,21 @@ import com.juul.kable.ServicesDiscoveredPeripheral
 import com.juul.kable.State
 import com.juul.kable.State.Connecting
 import com.juul.kable.State.Disconnected
+import com.juul.kable.State.Disconnected.Status
 import com.juul.kable.State.Disconnecting
 import com.juul.kable.WriteType
 import com.juul.kable.awaitConnect
 import com.juul.kable.btleplug.ffi.CancellationHandle
+import com.juul.kable.btleplug.ffi.ConnectionState
+import com.juul.kable.btleplug.ffi.DisconnectReason
 import com.juul.kable.btleplug.ffi.PeripheralCallbacks
+import com.juul.kable.btleplug.ffi.ReconnectEvent
+import com.juul.kable.btleplug.ffi.TimestampedValue
 import com.juul.kable.btleplug.ffi.isAdapterOn
 import com.juul.kable.coroutines.childSupervisor
 import com.juul.kable.logs.Logger
 import com.juul.kable.logs.Logging
 import com.juul.kable.sharedRepeatableAction
-import com.juul.kable.suspendUntil
 import com.juul.kable.unwrapCancellationException
 import kotlinx.coroutines.CoroutineScope
 import kotlinx.coroutines.CoroutineStart
@@ -43,6 +47,7 @@ import kotlinx.coroutines.runBlocking
 import kotlinx.coroutines.withContext
 import kotlinx.io.IOException
 import kotlin.coroutines.cancellation.CancellationException
+import com.juul.kable.btleplug.ffi.Characteristic as FfiCharacteristic
 import com.juul.kable.btleplug.ffi.Uuid as FfiUuid
 
 private const val DEFAULT_ATT_MTU = 23
@@ -64,31 +69,54 @@ internal class BtleplugPeripheral(
     override val services = _services.asStateFlow()
 
     private val callbacks: PeripheralCallbacks = object : PeripheralCallbacks {
+        override fun stateChanged(state: ConnectionState) {
+            logger.verbose { message = "Native state: $state" }
+            state.toState()?.let { _state.value = it }
+        }
+
         override fun connected() {
-            _state.value = Connecting.Services
+            logger.verbose { message = "Received connect" }
         }
 
-        override fun disconnected() {
-            logger.verbose { message = "Received disconnect" }
+        override fun disconnected(reason: DisconnectReason) {
+            logger.verbose { message = "Received disconnect, reason=$reason" }
             runBlocking {
                 connectAction.cancelAndJoin(CancellationException(NotConnectedException("Disconnected")))
             }
         }
 
-        override suspend fun notification(uuid: FfiUuid, data: ByteArray) {
-            val characteristic = ffi
-                .services()
-                .asSequence()
-                .flatMap { it.characteristics }
-                .single { it.uuid == uuid }
+        override fun reconnect(event: ReconnectEvent) {
+            // Reconnection is driven by Kable (see `connectAction`), so no policy is set natively.
+            logger.verbose { message = "Reconnect: $event" }
+        }
+
+        override fun subscriptionLost(characteristic: FfiCharacteristic) {
+            logger.warn {
+                message = "Subscription lost after reconnect: ${BtleplugCharacteristic(characteristic)}"
+            }
+        }
+
+        override suspend fun notification(characteristic: FfiCharacteristic, value: TimestampedValue) {
             observers.characteristicChanges.emit(
-                CharacteristicChange(BtleplugCharacteristic(characteristic), data),
+                CharacteristicChange(BtleplugCharacteristic(characteristic), value.data),
             )
         }
+
+        override suspend fun notificationBatch(characteristic: FfiCharacteristic, values: List<TimestampedValue>) {
+            // Batching is not enabled by Kable, but deliver values in order should it be.
+            val discovered = BtleplugCharacteristic(characteristic)
+            values.forEach { value ->
+                observers.characteristicChanges.emit(CharacteristicChange(discovered, value.data))
+            }
+        }
     }
 
     internal val ffi = com.juul.kable.btleplug.ffi.Peripheral(identifier.ffi, callbacks)
 
+    init {
+        ffi.state().toState()?.let { _state.value = it }
+    }
+
     private val observers = Observers<ByteArray>(this, logging, false) { cause ->
         logger.error(cause) { message = "Exception in observers" }
     }
@@ -105,10 +133,15 @@ internal class BtleplugPeripheral(
         }
 
     internal suspend fun getCharacteristic(characteristic: Characteristic) =
-        getCharacteristic(
-            characteristic.serviceUuid.toString(),
-            characteristic.characteristicUuid.toString(),
-        )
+        if (characteristic is BtleplugCharacteristic) {
+            // Discovered characteristics carry their instance, targeting duplicates where supported.
+            characteristic.characteristic
+        } else {
+            getCharacteristic(
+                characteristic.serviceUuid.toString(),
+                characteristic.characteristicUuid.toString(),
+            )
+        }
 
     private suspend fun getCharacteristic(service: FfiUuid, characteristic: FfiUuid) =
         withContext(Dispatchers.IO) {
@@ -133,10 +166,14 @@ internal class BtleplugPeripheral(
                 awaitCancellation()
             } finally {
                 withContext(NonCancellable) {
-                    _state.value = Disconnecting
                     taskScope.coroutineContext.job.cancelAndJoin()
-                    ffi.disconnect()
-                    _state.value = Disconnected()
+                    try {
+                        ffi.disconnect()
+                    } catch (e: Exception) {
+                        logger.warn(e) { message = "Failed to disconnect" }
+                    }
+                    // Kable's connection is torn down even if the native peripheral is still connected.
+                    _state.value = ffi.state().toState() as? Disconnected ?: Disconnected()
                 }
             }
         }
@@ -147,15 +184,17 @@ internal class BtleplugPeripheral(
         }
 
         logger.info { message = "Connecting" }
-        _state.value = Connecting.Bluetooth
         try {
-            if (!ffi.connect(cancellationHandle)) {
-                throw IOException("Failed to connect.")
+            try {
+                ffi.connect(cancellationHandle, timeout = null)
+            } catch (e: CancellationException) {
+                throw e
+            } catch (e: Exception) {
+                throw IOException("Failed to connect: ${e.message}", e)
             }
-            suspendUntil<Connecting.Services>()
 
             logger.info { message = "Discovering services" }
-            ffi.discoverServices()
+            ffi.discoverServices(cancellationHandle)
             _services.value = ffi.services().map(::BtleplugService)
             ServicesDiscoveredPeripheral(this).run { onServicesDiscovered() }
 
@@ -192,7 +231,9 @@ internal class BtleplugPeripheral(
     override suspend fun read(characteristic: Characteristic): ByteArray {
         logger.verbose { message = "Reading from $characteristic" }
         return withContext(Dispatchers.IO) {
-            ffi.read(getCharacteristic(characteristic))
+            // GATT operations need no cancellation handle: cancelling the calling coroutine drops the
+            // native operation, removing it from the peripheral's GATT queue if it has not started.
+            ffi.read(getCharacteristic(characteristic), options = null, cancellationHandle = null)
         }
     }
 
@@ -205,6 +246,8 @@ internal class BtleplugPeripheral(
                     descriptor.characteristicUuid.toString(),
                     descriptor.descriptorUuid.toString(),
                 ),
+                options = null,
+                cancellationHandle = null,
             )
         }
     }
@@ -212,7 +255,13 @@ internal class BtleplugPeripheral(
     override suspend fun write(characteristic: Characteristic, data: ByteArray, writeType: WriteType) {
         logger.verbose { message = "Writing to $characteristic, type=$writeType data=${data.size} bytes" }
         return withContext(Dispatchers.IO) {
-            ffi.write(getCharacteristic(characteristic), data, writeType.ffi())
+            ffi.write(
+                getCharacteristic(characteristic),
+                data,
+                writeType.ffi(),
+                options = null,
+                cancellationHandle = null,
+            )
         }
     }
 
@@ -226,6 +275,8 @@ internal class BtleplugPeripheral(
                     descriptor.descriptorUuid.toString(),
                 ),
                 data,
+                options = null,
+                cancellationHandle = null,
             )
         }
     }
@@ -242,6 +293,26 @@ internal class BtleplugPeripheral(
     override fun toString(): String = "Peripheral(identifier=$identifier)"
 }
 
+/**
+ * Kable's state for the native [ConnectionState], or `null` for [ConnectionState.Ready]: from there on, Kable
+ * configures observations and then reports the connection as established itself.
+ */
+private fun ConnectionState.toState(): State? = when (this) {
+    is ConnectionState.Disconnected -> Disconnected(reason?.toStatus())
+    ConnectionState.ScanningForDevice, ConnectionState.Connecting -> Connecting.Bluetooth
+    ConnectionState.DiscoveringServices -> Connecting.Services
+    ConnectionState.Ready -> null
+    ConnectionState.Disconnecting -> Disconnecting
+}
+
+private fun DisconnectReason.toStatus(): Status? = when (this) {
+    DisconnectReason.LocalRequest, DisconnectReason.AdapterPoweredOff -> Status.CentralDisconnected
+    DisconnectReason.RemoteUserTerminated -> Status.PeripheralDisconnected
+    DisconnectReason.ConnectionTimeout -> Status.Timeout
+    DisconnectReason.AuthenticationFailure, DisconnectReason.ConnectionFailed -> Status.Failed
+    is DisconnectReason.Unknown -> code?.let { Status.Unknown(it.toInt()) }
+}
+
 private fun WriteType.ffi() = when (this) {
     WriteType.WithResponse -> com.juul.kable.btleplug.ffi.WriteType.WITH_RESPONSE
     WriteType.WithoutResponse -> com.juul.kable.btleplug.ffi.WriteType.WITHOUT_RESPONSE